@@ -7,6 +7,7 @@ use crate::{
         WebsocketState, EVENT_BROKER,
     },
     ga,
+    game::{opening_book::OpeningBook, opening_suite::OpeningSuite, search::Score, tablebase::Tablebases},
     worker,
 };
 use shakmaty::{fen::Fen, Chess};
@@ -15,6 +16,7 @@ use std::{
     io,
     sync::{Arc, Mutex},
     thread,
+    sync::atomic::{AtomicBool, Ordering},
     time::{Duration},
 };
 use sysinfo::System;
@@ -27,7 +29,8 @@ pub struct ActiveMatch {
     pub white_player: String,
     pub black_player: String,
     pub san: String,
-    pub eval: i32,
+    pub eval: Score,
+    pub pv: String,
     pub material: i32,
 }
 
@@ -53,7 +56,11 @@ pub struct App {
     pub active_matches: HashMap<usize, ActiveMatch>,
     evolution_thread_handle: Option<thread::JoinHandle<()>>,
     evolution_should_quit: Arc<Mutex<bool>>,
+    evolution_stop_signal: Option<Arc<AtomicBool>>,
     match_id_counter: Arc<Mutex<usize>>,
+    tablebases: Option<Arc<Tablebases>>,
+    opening_book: Option<Arc<OpeningBook>>,
+    opening_suite: Option<Arc<OpeningSuite>>,
     selection_algorithm: SelectionAlgorithm,
     sts_leaderboard: Vec<StsLeaderboardEntry>,
     sts_hash_to_id_map: HashMap<u64, usize>,
@@ -64,10 +71,45 @@ pub struct App {
 
 impl App {
     #[cfg_attr(test, allow(dead_code))]
-    pub fn new(git_hash: String, _args: crate::Args) -> Self {
+    pub fn new(git_hash: String, args: crate::Args) -> Self {
         let mut system = System::new_all();
         system.refresh_all();
 
+        let tablebases = args.tablebase_path.and_then(|path| match Tablebases::open(&path) {
+            Ok(tablebases) => {
+                println!("Loaded tablebases up to {} pieces from {path}.", tablebases.max_pieces());
+                Some(Arc::new(tablebases))
+            }
+            Err(err) => {
+                eprintln!("Failed to load tablebases from {path}: {err}");
+                None
+            }
+        });
+        let opening_book = args.opening_book.and_then(|path| {
+            match OpeningBook::open(&path, args.book_depth, args.book_selection) {
+                Ok(book) => {
+                    println!("Loaded {} opening book entries from {path}.", book.entry_count());
+                    Some(Arc::new(book))
+                }
+                Err(err) => {
+                    eprintln!("Failed to load the opening book from {path}: {err}");
+                    None
+                }
+            }
+        });
+        let opening_suite = args.opening_suite.and_then(|path| {
+            match OpeningSuite::open(&path, args.reverse_colors) {
+                Ok(suite) => {
+                    println!("Loaded {} openings from {path}.", suite.opening_count());
+                    Some(Arc::new(suite))
+                }
+                Err(err) => {
+                    eprintln!("Failed to load the opening suite from {path}: {err}");
+                    None
+                }
+            }
+        });
+
         Self {
             should_quit: false,
             error_message: None,
@@ -86,7 +128,11 @@ impl App {
             active_matches: HashMap::new(),
             evolution_thread_handle: None,
             evolution_should_quit: Arc::new(Mutex::new(false)),
+            evolution_stop_signal: None,
             match_id_counter: Arc::new(Mutex::new(0)),
+            tablebases,
+            opening_book,
+            opening_suite,
             selection_algorithm: SelectionAlgorithm::SwissTournament,
             sts_leaderboard: Vec::new(),
             sts_hash_to_id_map: HashMap::new(),
@@ -160,8 +206,13 @@ impl App {
                     self.active_matches.clear();
                 }
                 Event::GenerationComplete(stats) => {
+                    let terminations: Vec<String> = stats
+                        .terminations
+                        .iter()
+                        .map(|(termination, count)| format!("{termination}: {count}"))
+                        .collect();
                     let log_message = format!(
-                        "Gen {}: {} matches (W:{} B:{} D:{}), ELOs (Top: {:.2}, Avg: {:.2}, Low: {:.2})",
+                        "Gen {}: {} matches (W:{} B:{} D:{}), ELOs (Top: {:.2}, Avg: {:.2}, Low: {:.2}), Endings ({})",
                         stats.generation_index,
                         stats.num_matches,
                         stats.white_wins,
@@ -169,7 +220,8 @@ impl App {
                         stats.draws,
                         stats.top_elo,
                         stats.average_elo,
-                        stats.lowest_elo
+                        stats.lowest_elo,
+                        terminations.join(", ")
                     );
                     println!("{log_message}");
                     EVENT_BROKER.publish(Event::LogUpdate(log_message));
@@ -199,9 +251,10 @@ impl App {
                     println!("{log_message}");
                     EVENT_BROKER.publish(Event::LogUpdate(log_message));
                 }
-                Event::ThinkingUpdate(match_id, _pv, eval) => {
+                Event::ThinkingUpdate(match_id, pv, eval) => {
                     if let Some(match_state) = self.active_matches.get_mut(&match_id) {
                         match_state.eval = eval;
+                        match_state.pv = pv;
                     }
                 }
                 Event::SearchStats(match_id, stats) => {
@@ -225,23 +278,35 @@ impl App {
                     self.should_quit = true;
                 }
                 Event::ForceQuit => {
+                    // Exits at once, without waiting for running searches to stop.
                     std::process::exit(0);
                 }
                 Event::ResetSimulation => {
-                    println!("Deleting evolution directory...");
-                    if let Err(e) = std::fs::remove_dir_all("evolution") {
-                        self.error_message = Some(format!("Failed to delete evolution directory: {e}"));
-                    } else {
-                        println!("Evolution directory deleted.");
-                    }
+                    // Wait for the evolution thread so it cannot write into the directories
+                    // while they are being deleted. Waiting happens off the event loop, which
+                    // keeps the UI and the web server responsive until the searches stop.
+                    self.stop_evolution();
+                    let handle = self.evolution_thread_handle.take();
+                    tokio::task::spawn_blocking(move || {
+                        if let Some(handle) = handle {
+                            let _ = handle.join();
+                        }
 
-                    println!("Deleting sts_results directory...");
-                    if let Err(e) = std::fs::remove_dir_all("sts_results") {
-                        self.error_message = Some(format!("Failed to delete sts_results directory: {e}"));
-                    } else {
-                        println!("sts_results directory deleted.");
-                    }
-                    std::process::exit(0);
+                        println!("Deleting evolution directory...");
+                        if let Err(e) = std::fs::remove_dir_all("evolution") {
+                            eprintln!("Failed to delete evolution directory: {e}");
+                        } else {
+                            println!("Evolution directory deleted.");
+                        }
+
+                        println!("Deleting sts_results directory...");
+                        if let Err(e) = std::fs::remove_dir_all("sts_results") {
+                            eprintln!("Failed to delete sts_results directory: {e}");
+                        } else {
+                            println!("sts_results directory deleted.");
+                        }
+                        std::process::exit(0);
+                    });
                 }
                 Event::WebsocketStateUpdate(_) | Event::LogUpdate(_) => {
                     // Ignore, this event is for the web client
@@ -274,13 +339,26 @@ impl App {
         let evolution_manager = ga::EvolutionManager::new(
             self.evolution_should_quit.clone(),
             self.match_id_counter.clone(),
+            self.tablebases.clone(),
+            self.opening_book.clone(),
+            self.opening_suite.clone(),
         );
+        self.evolution_stop_signal = Some(evolution_manager.stop_signal());
         let handle = thread::spawn(move || {
             evolution_manager.run();
         });
         self.evolution_thread_handle = Some(handle);
     }
 
+    /// Asks the evolution thread to shut down and cancels any searches it is waiting on.
+    #[cfg_attr(test, allow(dead_code))]
+    fn stop_evolution(&mut self) {
+        *self.evolution_should_quit.lock().unwrap() = true;
+        if let Some(stop_signal) = &self.evolution_stop_signal {
+            stop_signal.store(true, Ordering::Relaxed);
+        }
+    }
+
     #[cfg_attr(test, allow(dead_code))]
     fn get_websocket_state(&self) -> WebsocketState {
         WebsocketState {
@@ -315,6 +393,7 @@ impl App {
                                 black_player: m.black_player.clone(),
                                 san: m.san.clone(),
                                 eval: m.eval,
+                                pv: m.pv.clone(),
                                 material: m.material,
                             },
                         )
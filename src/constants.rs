@@ -1,5 +1,7 @@
 #![cfg_attr(test, allow(dead_code))]
 
+use std::ops::RangeInclusive;
+
 pub const NUM_ROUNDS: u32 = 7;
 pub const STARTING_ELO: f64 = 1200.0;
 
@@ -64,9 +66,33 @@ pub const CENTER_CONTROL_BONUS: i32 = 5;
 
 // Search
 pub const MATE_SCORE: i32 = 1_000_000;
+pub const MATE_THRESHOLD: i32 = MATE_SCORE - 1_000; // Scores beyond this are mate scores
+pub const TB_WIN_SCORE: i32 = MATE_THRESHOLD - 1_000; // Tablebase wins, minus the ply they were found at
+pub const MAX_PLY: usize = 64;
+pub const WIN_PROBABILITY_SCALE: f64 = 400.0; // Centipawns per unit of the logistic curve from scores to win probabilities
+pub const MAX_WIN_PROBABILITY_CP: i32 = 10_000; // Cap on scores converted back from win probabilities
+
+// Search Genes (bounds kept by randomization and mutation)
+pub const NMP_REDUCTION_RANGE: RangeInclusive<u8> = 1..=5;
+pub const NMP_MIN_DEPTH_RANGE: RangeInclusive<u8> = 1..=6;
+pub const NMP_EVAL_MARGIN_RANGE: RangeInclusive<i32> = 50..=1_000;
+pub const LMR_MIN_DEPTH_RANGE: RangeInclusive<u8> = 1..=6;
+pub const LMR_MIN_MOVE_INDEX_RANGE: RangeInclusive<u8> = 1..=8;
+pub const LMR_DIVISOR_RANGE: RangeInclusive<i32> = 100..=400; // In hundredths
+pub const ASPIRATION_WINDOW_RANGE: RangeInclusive<i32> = 10..=200;
+pub const LATE_MOVE_PRUNING_BASE_RANGE: RangeInclusive<u8> = 1..=10;
+pub const MCTS_EXPLORATION_RANGE: RangeInclusive<i32> = 10..=400; // In hundredths
+pub const MCTS_LEAF_DEPTH_RANGE: RangeInclusive<u8> = 1..=4;
 
 // Match Settings
 pub const ENABLE_MOVE_LIMIT: bool = false;
-
-// Evaluation Cache
-pub const EVALUATION_CACHE_SIZE: usize = 1_000_000;
\ No newline at end of file
+pub const MOVE_TIME_MS: u64 = 5_000; // Time budget per move in GA games
+pub const OPENING_BOOK_PLIES: usize = 12; // Default number of plies taken from the opening book
+pub const STS_MOVE_TIME_MS: u64 = 1_000; // Time budget per position in STS runs
+
+// Hash Tables
+pub const EVALUATION_CACHE_MB: usize = 16; // Default size of a searcher's evaluation cache
+pub const TRANSPOSITION_TABLE_MB: usize = 32; // Default size of a searcher's transposition table
+pub const HASH_MEMORY_BUDGET_MB: usize = 2_048; // Shared by the hash tables of all players in a round
+pub const MIN_TABLE_MB: usize = 1; // Smallest table a split of the budget may leave a player
+pub const MCTS_CACHE_MB: usize = 16; // Size of an MCTS searcher's cache of visit statistics
\ No newline at end of file
@@ -6,9 +6,12 @@ use actix::Message;
 use once_cell::sync::Lazy;
 use serde::Serialize;
 use shakmaty::{fen::Fen, Chess};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use tokio::sync::broadcast;
 
+use crate::ga::Termination;
+use crate::game::search::{Score, SearchStats};
+
 #[derive(Debug, Clone, Serialize)]
 pub struct ComponentState {
     pub label: String,
@@ -61,7 +64,8 @@ pub struct ActiveMatchState {
     pub white_player: String,
     pub black_player: String,
     pub san: String,
-    pub eval: i32,
+    pub eval: Score, // Score of the last search, for the side to move in `board`
+    pub pv: String, // Principal variation of the last search, in SAN
     pub material: i32,
 }
 
@@ -90,6 +94,8 @@ pub struct GenerationStats {
     pub white_wins: usize,
     pub black_wins: usize,
     pub draws: usize,
+    /// The number of games that ended in each way.
+    pub terminations: BTreeMap<Termination, usize>,
     pub top_elo: f64,
     pub average_elo: f64,
     pub lowest_elo: f64,
@@ -119,8 +125,8 @@ pub enum Event {
     GenerationComplete(GenerationStats),
     MatchStarted(usize, String, String),
     MatchCompleted(usize, MatchResult),
-    ThinkingUpdate(usize, String, i32),
-    SearchStats(usize, String),
+    ThinkingUpdate(usize, String, Score), // match id, PV in SAN, score for the side to move after the searched move
+    SearchStats(usize, SearchStats),
     MovePlayed(usize, String, i32, Chess),
     StatusUpdate(String),
     LogUpdate(String),
@@ -137,7 +143,8 @@ impl From<&Chess> for ActiveMatchState {
             white_player: String::new(),
             black_player: String::new(),
             san: String::new(),
-            eval: 0,
+            eval: Score::default(),
+            pv: String::new(),
             material: 0,
         }
     }
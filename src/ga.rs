@@ -1,22 +1,34 @@
 #![cfg_attr(test, allow(dead_code))]
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs;
 use std::hash::{Hash, Hasher};
 use std::path::{Path, PathBuf};
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use rand::Rng;
 use rand::distributions::Distribution;
 use rand::seq::SliceRandom;
-use shakmaty::{Chess, Position, zobrist::{Zobrist64, ZobristHash}, EnPassantMode};
+use shakmaty::{Chess, Move, Position, zobrist::{Zobrist64, ZobristHash}, EnPassantMode};
 use shakmaty::san::SanPlus;
 use serde::{Deserialize, Serialize};
 
-use crate::constants::{NUM_ROUNDS, STARTING_ELO, POPULATION_SIZE, MUTATION_CHANCE, ENABLE_MOVE_LIMIT};
+use crate::constants::{NUM_ROUNDS, STARTING_ELO, POPULATION_SIZE, MUTATION_CHANCE, ENABLE_MOVE_LIMIT, MOVE_TIME_MS, HASH_MEMORY_BUDGET_MB};
+use crate::constants::{
+    ASPIRATION_WINDOW_RANGE, LATE_MOVE_PRUNING_BASE_RANGE, LMR_DIVISOR_RANGE, LMR_MIN_DEPTH_RANGE,
+    LMR_MIN_MOVE_INDEX_RANGE, MCTS_EXPLORATION_RANGE, MCTS_LEAF_DEPTH_RANGE, NMP_EVAL_MARGIN_RANGE,
+    NMP_MIN_DEPTH_RANGE, NMP_REDUCTION_RANGE,
+};
+use std::ops::RangeInclusive;
 use crate::event::{Event, MatchResult, EVENT_BROKER, SelectionAlgorithm};
-use crate::game::search::{evaluation_cache::EvaluationCache, SearchAlgorithm, SearchConfig};
+use crate::game::search::{SearchAlgorithm, SearchConfig, SearchLimits, SearchTables};
+use crate::game::opening_book::OpeningBook;
+use crate::game::opening_suite::{self, OpeningSuite};
+use crate::game::tablebase::Tablebases;
+use shakmaty_syzygy::Wdl;
 use crate::sts::{StsResult, StsRunner};
 use std::io;
+use std::time::Duration;
 
 const EVOLUTION_DIR: &str = "evolution";
 
@@ -50,7 +62,7 @@ impl SelectionModeConfig {
         }
     }
 }
-use crate::worker::{push_job, Job};
+use crate::worker::{push_job, Job, SearcherHandle};
 use tokio::sync::{mpsc, oneshot, Semaphore};
 
 /// Loads the configuration for the current generation, creating it if it doesn't exist.
@@ -86,18 +98,27 @@ pub struct GenerationConfig {
     pub selection_algorithm: SelectionAlgorithm,
 }
 
-/// Manages evaluation caches for all players in the tournament.
-/// Caches are created on-demand and automatically destroyed when no longer in use.
+/// Manages the hash tables (evaluation cache and transposition table) for all players in the
+/// tournament. Tables are created on-demand and automatically destroyed when no longer in use.
+/// Each configuration gets its own tables, since cached scores depend on the evaluation weights.
 struct CacheManager {
-    caches: Arc<Mutex<HashMap<SearchConfig, Arc<Mutex<EvaluationCache>>>>>,
+    caches: Arc<Mutex<HashMap<SearchConfig, SearchTables>>>,
     usage_count: Arc<Mutex<HashMap<SearchConfig, usize>>>,
+    /// The size of each player's tables, so that all players of concurrent games stay
+    /// within `HASH_MEMORY_BUDGET_MB`.
+    tables_mb: usize,
+    /// Shared by all players.
+    tablebases: Option<Arc<Tablebases>>,
 }
 
 impl CacheManager {
-    fn new() -> Self {
+    fn new(tablebases: Option<Arc<Tablebases>>) -> Self {
+        let players = 2 * max_concurrent_matches();
         Self {
             caches: Arc::new(Mutex::new(HashMap::new())),
             usage_count: Arc::new(Mutex::new(HashMap::new())),
+            tables_mb: (HASH_MEMORY_BUDGET_MB / players).max(1),
+            tablebases,
         }
     }
 
@@ -109,16 +130,19 @@ impl CacheManager {
         let mut caches = self.caches.lock().unwrap();
         let mut usage_count = self.usage_count.lock().unwrap();
 
-        let cache = caches
+        let tables = caches
             .entry(config.clone())
-            .or_insert_with(|| Arc::new(Mutex::new(EvaluationCache::new())))
+            .or_insert_with(|| SearchTables {
+                tablebases: self.tablebases.clone(),
+                ..SearchTables::for_algorithm(&config.search_algorithm, self.tables_mb)
+            })
             .clone();
 
         *usage_count.entry(config.clone()).or_insert(0) += 1;
 
         CacheGuard {
             config: config.clone(),
-            _cache: cache.clone(),
+            tables,
             cache_manager: self.clone(),
         }
     }
@@ -144,18 +168,28 @@ impl Clone for CacheManager {
         Self {
             caches: self.caches.clone(),
             usage_count: self.usage_count.clone(),
+            tables_mb: self.tables_mb,
+            tablebases: self.tablebases.clone(),
         }
     }
 }
 
-/// A guard that holds a reference to a player's cache.
+/// A guard that holds a reference to a player's tables.
 /// When this guard is dropped, it notifies the `CacheManager` to decrement the usage count.
 struct CacheGuard {
     config: SearchConfig,
-    _cache: Arc<Mutex<EvaluationCache>>,
+    tables: SearchTables,
     cache_manager: CacheManager,
 }
 
+impl CacheGuard {
+    /// Creates a searcher for one player of a game. It works with this player's tables, and
+    /// keeps its move history for the whole game.
+    fn new_searcher(&self) -> SearcherHandle {
+        SearcherHandle::for_config(&self.config, self.tables.clone())
+    }
+}
+
 impl Drop for CacheGuard {
     fn drop(&mut self) {
         self.cache_manager.release_cache(&self.config);
@@ -163,24 +197,50 @@ impl Drop for CacheGuard {
 }
 
 
+/// The number of games played at the same time in a round.
+fn max_concurrent_matches() -> usize {
+    num_cpus::get().max(1)
+}
+
 /// Manages the evolution process in a background thread.
 #[derive(Clone)]
 pub struct EvolutionManager {
     should_quit: Arc<Mutex<bool>>,
     match_id_counter: Arc<Mutex<usize>>,
+    stop_signal: Arc<AtomicBool>,
+    /// Endgame tablebases for the searches, also used to adjudicate games.
+    tablebases: Option<Arc<Tablebases>>,
+    /// Plays the first moves of each game, so that games start from varied positions.
+    opening_book: Option<Arc<OpeningBook>>,
+    /// Start positions assigned to the pairings.
+    opening_suite: Option<Arc<OpeningSuite>>,
 }
 
 impl EvolutionManager {
     pub fn new(
         should_quit: Arc<Mutex<bool>>,
         match_id_counter: Arc<Mutex<usize>>,
+        tablebases: Option<Arc<Tablebases>>,
+        opening_book: Option<Arc<OpeningBook>>,
+        opening_suite: Option<Arc<OpeningSuite>>,
     ) -> Self {
         Self {
             should_quit,
             match_id_counter,
+            stop_signal: Arc::new(AtomicBool::new(false)),
+            tablebases,
+            opening_book,
+            opening_suite,
         }
     }
 
+    /// Returns the flag that cancels every search this manager has pushed to the workers.
+    /// Setting it makes in-flight games stop at their next move so the evolution thread can
+    /// shut down without waiting for the current searches to finish.
+    pub fn stop_signal(&self) -> Arc<AtomicBool> {
+        self.stop_signal.clone()
+    }
+
     fn send_status(&self, message: String) -> Result<(), ()> {
         EVENT_BROKER.publish(Event::StatusUpdate(message));
         Ok(())
@@ -223,7 +283,7 @@ impl EvolutionManager {
         }
 
 
-        let cache_manager = CacheManager::new();
+        let cache_manager = CacheManager::new(self.tablebases.clone());
 
         loop {
             if *self.should_quit.lock().unwrap() {
@@ -247,6 +307,11 @@ impl EvolutionManager {
             // Only run the tournament for SwissTournament generations.
             if config.selection_algorithm == SelectionAlgorithm::SwissTournament {
                 self.run_tournament(&mut generation, &cache_manager).await?;
+                if *self.should_quit.lock().unwrap() {
+                    // The tournament was interrupted; it will be resumed on the next start.
+                    self.send_status("Shutdown signal received, stopping evolution.".to_string())?;
+                    break Ok(());
+                }
             } else {
                 self.send_status(format!(
                     "Generation {generation_index} is an STS evaluation generation. Skipping tournament."
@@ -608,14 +673,21 @@ impl EvolutionManager {
     async fn run_sts_for_population(&self, population: &Population) -> Result<Vec<StsResult>, ()> {
         let (tx, mut rx) = mpsc::channel(population.individuals.len());
         let total_tasks = population.individuals.len();
+        // One run per worker at a time, so that the runners' tables stay within the hash
+        // memory budget.
+        let semaphore = Arc::new(Semaphore::new(max_concurrent_matches()));
 
         for individual in &population.individuals {
             let tx_clone = tx.clone();
             let config = individual.config.clone();
             let individual_id = individual.id;
+            let stop_signal = self.stop_signal.clone();
+            let semaphore = semaphore.clone();
 
             tokio::spawn(async move {
-                let mut sts_runner = StsRunner::new(config);
+                // Waiting here rather than before spawning keeps the status updates below flowing.
+                let _permit = semaphore.acquire_owned().await.unwrap();
+                let mut sts_runner = StsRunner::new(config).with_stop_signal(stop_signal);
                 // The run method now internally handles waiting for the result
                 // and returns the final StsResult.
                 if let Some(mut result) = sts_runner.run().await {
@@ -788,6 +860,7 @@ impl EvolutionManager {
         let white_wins = final_generation.matches.iter().filter(|m| m.result == "1-0").count();
         let black_wins = final_generation.matches.iter().filter(|m| m.result == "0-1").count();
         let draws = final_generation.matches.iter().filter(|m| m.result == "1/2-1/2").count();
+        let terminations = termination_counts(&final_generation.matches);
         let elos: Vec<f64> = final_generation.population.individuals.iter().map(|i| i.elo).collect();
     let top_elo = elos.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
     let lowest_elo = elos.iter().cloned().fold(f64::INFINITY, f64::min);
@@ -800,6 +873,7 @@ impl EvolutionManager {
         white_wins,
         black_wins,
         draws,
+        terminations,
         top_elo,
         average_elo,
         lowest_elo,
@@ -856,32 +930,48 @@ fn generate_pairings(&self, generation: &mut Generation, round: u32) -> Vec<Matc
                         &generation.black_games_played,
                     );
 
-                    let white_player_name = format!("individual_{}.json", white.id);
-                    let black_player_name = format!("individual_{}.json", black.id);
-
-                    // Check if this match has already been played in this round
-                    let match_played = generation.matches.iter().any(|m| {
-                        m.round == round
-                            && m.white_player_name == white_player_name
-                            && m.black_player_name == black_player_name
-                    });
-
-                    if !match_played {
-                        round_matches.push(Match {
-                            round,
-                            white_player_name,
-                            black_player_name,
-                            status: "pending".to_string(),
-                            result: "".to_string(),
-                            san: "".to_string(),
+                    let opening = self
+                        .opening_suite
+                        .as_ref()
+                        .map(|suite| suite.choose(&mut rand::thread_rng()).to_string())
+                        .unwrap_or_default();
+                    let reversed = self.opening_suite.as_ref().is_some_and(|suite| suite.play_reversed());
+                    let mut colors = vec![(white.id, black.id)];
+                    if reversed {
+                        colors.push((black.id, white.id));
+                    }
+
+                    for (white_id, black_id) in colors {
+                        let white_player_name = format!("individual_{white_id}.json");
+                        let black_player_name = format!("individual_{black_id}.json");
+
+                        // Check if this match has already been played in this round
+                        let match_played = generation.matches.iter().any(|m| {
+                            m.round == round
+                                && m.white_player_name == white_player_name
+                                && m.black_player_name == black_player_name
                         });
+
+                        if !match_played {
+                            round_matches.push(Match {
+                                round,
+                                white_player_name,
+                                black_player_name,
+                                status: "pending".to_string(),
+                                result: "".to_string(),
+                                san: "".to_string(),
+                                opening: opening.clone(),
+                                termination: None,
+                            });
+                        }
+
+                        *generation.white_games_played.entry(white_id).or_insert(0) += 1;
+                        *generation.black_games_played.entry(black_id).or_insert(0) += 1;
                     }
 
                     generation.previous_matchups.insert(matchup);
                     paired_ids.insert(p1.id);
                     paired_ids.insert(p2.id);
-                    *generation.white_games_played.entry(white.id).or_insert(0) += 1;
-                    *generation.black_games_played.entry(black.id).or_insert(0) += 1;
 
                     opponent_found = true;
                     break;
@@ -938,8 +1028,7 @@ fn generate_pairings(&self, generation: &mut Generation, round: u32) -> Vec<Matc
         EVENT_BROKER.publish(Event::TournamentStart(round as usize, total_matches, skipped_matches));
 
         let mut match_tasks = Vec::new();
-        let max_concurrent_matches = num_cpus::get().max(1);
-        let semaphore = Arc::new(Semaphore::new(max_concurrent_matches));
+        let semaphore = Arc::new(Semaphore::new(max_concurrent_matches()));
 
         for mut game_match in pending_matches {
             if *self.should_quit.lock().unwrap() {
@@ -977,8 +1066,9 @@ fn generate_pairings(&self, generation: &mut Generation, round: u32) -> Vec<Matc
                     game_match.black_player_name.clone(),
                 ));
 
-                if let Ok((result, san)) = self_clone.play_game(match_id, &white_config, &black_config, &white_cache_guard, &black_cache_guard).await {
+                if let Ok((result, termination, san)) = self_clone.play_game(match_id, &game_match.opening, &white_config, &black_config, &white_cache_guard, &black_cache_guard).await {
                     game_match.san = san;
+                    game_match.termination = Some(termination);
                     game_match.status = "completed".to_string();
 
                     let white_id = parse_id_from_name(&game_match.white_player_name);
@@ -1034,106 +1124,125 @@ fn generate_pairings(&self, generation: &mut Generation, round: u32) -> Vec<Matc
     async fn play_game(
         &self,
         match_id: usize,
+        opening: &str,
         white_config: &SearchConfig,
         black_config: &SearchConfig,
-        _white_cache_guard: &CacheGuard, // Caching is now per-worker
-        _black_cache_guard: &CacheGuard,
-    ) -> Result<(GameResult, String), ()> {
-        let mut pos = Chess::default();
+        white_cache_guard: &CacheGuard,
+        black_cache_guard: &CacheGuard,
+    ) -> Result<(GameResult, Termination, String), ()> {
+        let white_searcher = white_cache_guard.new_searcher();
+        let black_searcher = black_cache_guard.new_searcher();
+        let start = match opening_suite::start_position(opening) {
+            Ok(start) => start,
+            Err(err) => {
+                self.send_status(format!("Match {match_id} has an invalid opening: {err}"))?;
+                return Err(());
+            }
+        };
+        let mut pos = start.clone();
         let mut sans = Vec::new();
         let mut position_counts: HashMap<u64, u32> = HashMap::new();
-        let mut game_result_override = None;
-
-        while !pos.is_game_over() {
-            if ENABLE_MOVE_LIMIT && sans.len() >= 200 {
-                game_result_override = Some(GameResult::Draw);
-                break;
+        let mut history: Vec<Zobrist64> = Vec::new();
+
+        if let Some(book) = &self.opening_book {
+            while let Some(m) = book.pick_move(&pos, sans.len(), &mut rand::thread_rng()) {
+                let zobrist_hash: Zobrist64 = pos.zobrist_hash(EnPassantMode::Legal);
+                *position_counts.entry(zobrist_hash.0).or_insert(0) += 1;
+                history.push(zobrist_hash);
+                play_and_publish(match_id, &mut pos, &mut sans, m);
             }
+        }
 
+        let (result, termination) = loop {
             let zobrist_hash: Zobrist64 = pos.zobrist_hash(EnPassantMode::Legal);
             let count = position_counts.entry(zobrist_hash.0).or_insert(0);
             *count += 1;
-            if *count >= 4 {
-                game_result_override = Some(GameResult::Draw);
-                break;
+            if let Some(end) = rules_result(&pos, *count) {
+                break end;
             }
 
-            let config = if pos.turn().is_white() {
-                white_config.clone()
+            if ENABLE_MOVE_LIMIT && sans.len() >= 200 {
+                break (GameResult::Draw, Termination::Adjudication);
+            }
+
+            // Positions in the tablebases are decided by them without playing on.
+            if let Some(result) = self.tablebases.as_deref().and_then(|tb| tablebase_result(tb, &pos)) {
+                break (result, Termination::Adjudication);
+            }
+
+            let (config, searcher) = if pos.turn().is_white() {
+                (white_config.clone(), white_searcher.clone())
             } else {
-                black_config.clone()
+                (black_config.clone(), black_searcher.clone())
             };
 
-            let thinking_msg = format!("AI is thinking for {:?}...", pos.turn());
-            EVENT_BROKER.publish(Event::ThinkingUpdate(match_id, thinking_msg, 0));
+            // Both sides get the same time per move, with their evolved depth as an upper bound.
+            let limits = SearchLimits {
+                movetime: Some(Duration::from_millis(MOVE_TIME_MS)),
+                stop: Some(self.stop_signal.clone()),
+                ..SearchLimits::depth(config.search_depth)
+            };
 
             // Create a oneshot channel to get the result from the worker.
             let (result_tx, result_rx) = oneshot::channel();
+            // Only positions since the last capture or pawn move can be repeated.
+            let repeatable = history.len().min(pos.halfmoves() as usize);
+            let verbose = matches!(config.search_algorithm, SearchAlgorithm::Mcts | SearchAlgorithm::MctsAb);
             let job = Job::FindBestMove {
                 pos: pos.clone(),
+                history: history[history.len() - repeatable..].to_vec(),
                 config,
+                limits,
+                searcher,
+                build_tree: true,
+                verbose,
                 result_tx,
             };
             push_job(job);
 
             // Await the result from the worker.
-            if let Ok((best_move, eval, _final_tree, stats_string)) = result_rx.await {
-                let thinking_done_msg = format!("AI finished thinking for {:?}...", pos.turn());
-                EVENT_BROKER.publish(Event::ThinkingUpdate(match_id, thinking_done_msg, eval));
-                if let Some(stats) = stats_string {
-                    EVENT_BROKER.publish(Event::SearchStats(match_id, stats));
-                }
-
-                if let Some(m) = best_move {
-                    let san = SanPlus::from_move(pos.clone(), m);
-                    sans.push(san);
-                    pos.play_unchecked(m);
-
-                    let material_diff = calculate_material_difference(&pos);
-                    let last_san = sans.last().map(|s| s.to_string()).unwrap_or_default();
-                    EVENT_BROKER.publish(Event::MovePlayed(
-                        match_id,
-                        last_san,
-                        material_diff,
-                        pos.clone(),
-                    ));
+            let result = result_rx.await;
+            if self.stop_signal.load(Ordering::Relaxed) {
+                // The search was cancelled; abandon the game so it is replayed after a restart.
+                return Err(());
+            }
+            if let Ok(search_result) = result {
+                let pv = search_result.pv_san(&pos).join(" ");
+                EVENT_BROKER.publish(Event::SearchStats(match_id, search_result.stats));
+
+                if let Some(m) = search_result.best_move {
+                    history.push(zobrist_hash);
+                    play_and_publish(match_id, &mut pos, &mut sans, m);
+                    // The dashboard shows the position after the move, so the score is turned
+                    // around to the side now to move.
+                    EVENT_BROKER.publish(Event::ThinkingUpdate(match_id, pv, search_result.score.after_move()));
                 } else {
-                    // This case can happen if the AI finds no legal moves,
-                    // which shouldn't happen if the game isn't over.
-                    // We'll treat it as a draw to be safe.
-                    game_result_override = Some(GameResult::Draw);
-                    break;
+                    // A panicking worker returns an empty result, and the game is not over, so
+                    // there are legal moves. We'll treat it as a draw to be safe.
+                    break (GameResult::Draw, Termination::WorkerFailure);
                 }
             } else {
                 // The sender was dropped, maybe the worker panicked.
                 // Log an error and end the game as a draw.
                 let error_msg = format!("Error: Worker for match {match_id} failed to return a move.");
                 EVENT_BROKER.publish(Event::StatusUpdate(error_msg));
-                game_result_override = Some(GameResult::Draw);
-                break;
-            }
-        }
-
-        let result = if let Some(res) = game_result_override {
-            res
-        } else {
-            let outcome = pos.outcome();
-            match outcome.winner() {
-                Some(shakmaty::Color::White) => GameResult::WhiteWin,
-                Some(shakmaty::Color::Black) => GameResult::BlackWin,
-                None => GameResult::Draw,
+                break (GameResult::Draw, Termination::WorkerFailure);
             }
         };
 
         let mut pgn = String::new();
+        let first_ply = (start.fullmoves().get() as usize - 1) * 2 + start.turn().is_black() as usize;
         for (i, san) in sans.iter().enumerate() {
-            if i % 2 == 0 {
-                pgn.push_str(&format!("{}. ", i / 2 + 1));
+            let ply = first_ply + i;
+            if ply.is_multiple_of(2) {
+                pgn.push_str(&format!("{}. ", ply / 2 + 1));
+            } else if i == 0 {
+                pgn.push_str(&format!("{}... ", ply / 2 + 1));
             }
             pgn.push_str(&format!("{san} "));
         }
 
-        Ok((result, pgn))
+        Ok((result, termination, pgn))
     }
 
     fn assign_colors<'a>(
@@ -1271,6 +1380,53 @@ pub struct Match {
     pub status: String, // "pending", "completed"
     pub result: String, // "1-0", "0-1", "1/2-1/2", ""
     pub san: String,
+    /// FEN of the start position, or empty for the standard start position.
+    #[serde(default)]
+    pub opening: String,
+    /// How the game ended, or `None` while it is pending.
+    #[serde(default)]
+    pub termination: Option<Termination>,
+}
+
+/// How a GA game ended.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum Termination {
+    Checkmate,
+    Stalemate,
+    /// The same position occurred three times.
+    Repetition,
+    /// A hundred plies were played without a capture or a pawn move.
+    FiftyMoves,
+    InsufficientMaterial,
+    /// Decided by the tablebases or the move limit without playing on.
+    Adjudication,
+    /// A worker returned no move. The game is scored as a draw.
+    WorkerFailure,
+}
+
+impl std::fmt::Display for Termination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Termination::Checkmate => "checkmate",
+            Termination::Stalemate => "stalemate",
+            Termination::Repetition => "repetition",
+            Termination::FiftyMoves => "fifty moves",
+            Termination::InsufficientMaterial => "insufficient material",
+            Termination::Adjudication => "adjudication",
+            Termination::WorkerFailure => "worker failure",
+        };
+        f.write_str(name)
+    }
+}
+
+/// The number of completed matches that ended in each way.
+pub fn termination_counts(matches: &[Match]) -> BTreeMap<Termination, usize> {
+    let mut counts = BTreeMap::new();
+    for termination in matches.iter().filter_map(|m| m.termination) {
+        *counts.entry(termination).or_insert(0) += 1;
+    }
+    counts
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
@@ -1339,6 +1495,55 @@ enum GameResult {
     Draw,
 }
 
+/// Plays `m` in a GA game and notifies the front-ends.
+fn play_and_publish(match_id: usize, pos: &mut Chess, sans: &mut Vec<SanPlus>, m: Move) {
+    let san = SanPlus::from_move(pos.clone(), m);
+    sans.push(san);
+    pos.play_unchecked(m);
+
+    let material_diff = calculate_material_difference(pos);
+    let last_san = sans.last().map(|s| s.to_string()).unwrap_or_default();
+    EVENT_BROKER.publish(Event::MovePlayed(
+        match_id,
+        last_san,
+        material_diff,
+        pos.clone(),
+    ));
+}
+
+/// The result of `pos` under the rules of chess, if the game is over. `repetitions` is the
+/// number of times `pos` has occurred in the game, including this one. Checkmate takes
+/// precedence over the fifty-move rule.
+fn rules_result(pos: &Chess, repetitions: u32) -> Option<(GameResult, Termination)> {
+    if pos.is_checkmate() {
+        let result = if pos.turn().is_white() { GameResult::BlackWin } else { GameResult::WhiteWin };
+        return Some((result, Termination::Checkmate));
+    }
+    let termination = if pos.is_stalemate() {
+        Termination::Stalemate
+    } else if pos.is_insufficient_material() {
+        Termination::InsufficientMaterial
+    } else if pos.halfmoves() >= 100 {
+        Termination::FiftyMoves
+    } else if repetitions >= 3 {
+        Termination::Repetition
+    } else {
+        return None;
+    };
+    Some((GameResult::Draw, termination))
+}
+
+/// The result of `pos` according to the tablebases, if they decide it under the fifty-move rule.
+fn tablebase_result(tablebases: &Tablebases, pos: &Chess) -> Option<GameResult> {
+    let wdl = tablebases.probe_wdl(pos)?.unambiguous()?;
+    let result = match (wdl, pos.turn()) {
+        (Wdl::Win, shakmaty::Color::White) | (Wdl::Loss, shakmaty::Color::Black) => GameResult::WhiteWin,
+        (Wdl::Win, shakmaty::Color::Black) | (Wdl::Loss, shakmaty::Color::White) => GameResult::BlackWin,
+        _ => GameResult::Draw,
+    };
+    Some(result)
+}
+
 /// Calculates the new ELO ratings for two players based on the game outcome.
 ///
 /// # Arguments
@@ -1372,7 +1577,7 @@ fn parse_id_from_name(name: &str) -> usize {
 fn crossover(p1: &SearchConfig, p2: &SearchConfig, rng: &mut impl Rng) -> SearchConfig {
     SearchConfig {
         search_depth: (if rng.gen_bool(0.5) { p1.search_depth } else { p2.search_depth }).clamp(15, 20),
-        search_algorithm: SearchAlgorithm::Pvs,
+        search_algorithm: if rng.gen_bool(0.5) { p1.search_algorithm.clone() } else { p2.search_algorithm.clone() },
         use_aspiration_windows: if rng.gen_bool(0.5) { p1.use_aspiration_windows } else { p2.use_aspiration_windows },
         use_history_heuristic: if rng.gen_bool(0.5) { p1.use_history_heuristic } else { p2.use_history_heuristic },
         use_killer_moves: if rng.gen_bool(0.5) { p1.use_killer_moves } else { p2.use_killer_moves },
@@ -1383,6 +1588,18 @@ fn crossover(p1: &SearchConfig, p2: &SearchConfig, rng: &mut impl Rng) -> Search
         use_lmr: if rng.gen_bool(0.5) { p1.use_lmr } else { p2.use_lmr },
         use_futility_pruning: if rng.gen_bool(0.5) { p1.use_futility_pruning } else { p2.use_futility_pruning },
         use_delta_pruning: if rng.gen_bool(0.5) { p1.use_delta_pruning } else { p2.use_delta_pruning },
+        use_qs_checks: if rng.gen_bool(0.5) { p1.use_qs_checks } else { p2.use_qs_checks },
+        use_check_extensions: if rng.gen_bool(0.5) { p1.use_check_extensions } else { p2.use_check_extensions },
+        use_recapture_extensions: if rng.gen_bool(0.5) { p1.use_recapture_extensions } else { p2.use_recapture_extensions },
+        use_passed_pawn_extensions: if rng.gen_bool(0.5) { p1.use_passed_pawn_extensions } else { p2.use_passed_pawn_extensions },
+        use_singular_extensions: if rng.gen_bool(0.5) { p1.use_singular_extensions } else { p2.use_singular_extensions },
+        use_reverse_futility_pruning: if rng.gen_bool(0.5) { p1.use_reverse_futility_pruning } else { p2.use_reverse_futility_pruning },
+        use_razoring: if rng.gen_bool(0.5) { p1.use_razoring } else { p2.use_razoring },
+        use_late_move_pruning: if rng.gen_bool(0.5) { p1.use_late_move_pruning } else { p2.use_late_move_pruning },
+        use_probcut: if rng.gen_bool(0.5) { p1.use_probcut } else { p2.use_probcut },
+        use_adaptive_nmp: if rng.gen_bool(0.5) { p1.use_adaptive_nmp } else { p2.use_adaptive_nmp },
+        use_counter_moves: if rng.gen_bool(0.5) { p1.use_counter_moves } else { p2.use_counter_moves },
+        use_continuation_history: if rng.gen_bool(0.5) { p1.use_continuation_history } else { p2.use_continuation_history },
         pawn_structure_weight: if rng.gen_bool(0.5) { p1.pawn_structure_weight } else { p2.pawn_structure_weight },
         piece_mobility_weight: if rng.gen_bool(0.5) { p1.piece_mobility_weight } else { p2.piece_mobility_weight },
         king_safety_weight: if rng.gen_bool(0.5) { p1.king_safety_weight } else { p2.king_safety_weight },
@@ -1409,6 +1626,19 @@ fn crossover(p1: &SearchConfig, p2: &SearchConfig, rng: &mut impl Rng) -> Search
         opponent_weakness_weight: if rng.gen_bool(0.5) { p1.opponent_weakness_weight } else { p2.opponent_weakness_weight },
         contempt_factor: if rng.gen_bool(0.5) { p1.contempt_factor } else { p2.contempt_factor },
         draw_avoidance_margin: if rng.gen_bool(0.5) { p1.draw_avoidance_margin } else { p2.draw_avoidance_margin },
+        reverse_futility_margin: if rng.gen_bool(0.5) { p1.reverse_futility_margin } else { p2.reverse_futility_margin },
+        razoring_margin: if rng.gen_bool(0.5) { p1.razoring_margin } else { p2.razoring_margin },
+        late_move_pruning_base: if rng.gen_bool(0.5) { p1.late_move_pruning_base } else { p2.late_move_pruning_base },
+        probcut_margin: if rng.gen_bool(0.5) { p1.probcut_margin } else { p2.probcut_margin },
+        nmp_reduction: if rng.gen_bool(0.5) { p1.nmp_reduction } else { p2.nmp_reduction },
+        nmp_min_depth: if rng.gen_bool(0.5) { p1.nmp_min_depth } else { p2.nmp_min_depth },
+        nmp_eval_margin: if rng.gen_bool(0.5) { p1.nmp_eval_margin } else { p2.nmp_eval_margin },
+        lmr_min_depth: if rng.gen_bool(0.5) { p1.lmr_min_depth } else { p2.lmr_min_depth },
+        lmr_min_move_index: if rng.gen_bool(0.5) { p1.lmr_min_move_index } else { p2.lmr_min_move_index },
+        lmr_divisor: if rng.gen_bool(0.5) { p1.lmr_divisor } else { p2.lmr_divisor },
+        aspiration_window: if rng.gen_bool(0.5) { p1.aspiration_window } else { p2.aspiration_window },
+        mcts_exploration: if rng.gen_bool(0.5) { p1.mcts_exploration } else { p2.mcts_exploration },
+        mcts_leaf_depth: if rng.gen_bool(0.5) { p1.mcts_leaf_depth } else { p2.mcts_leaf_depth },
     }
 }
 
@@ -1422,7 +1652,10 @@ fn mutate(config: &mut SearchConfig, rng: &mut impl Rng) {
         }
         config.search_depth = config.search_depth.clamp(15, 20);
     }
-    config.search_algorithm = SearchAlgorithm::Pvs;
+    if rng.gen_bool(0.03) {
+        let others: Vec<_> = SearchAlgorithm::EVOLVABLE.into_iter().filter(|a| *a != config.search_algorithm).collect();
+        config.search_algorithm = others[rng.gen_range(0..others.len())].clone();
+    }
     // Mutate booleans with a 3% chance
     if rng.gen_bool(0.03) { config.use_aspiration_windows = !config.use_aspiration_windows; }
     if rng.gen_bool(0.03) { config.use_history_heuristic = !config.use_history_heuristic; }
@@ -1433,6 +1666,18 @@ fn mutate(config: &mut SearchConfig, rng: &mut impl Rng) {
     if rng.gen_bool(0.03) { config.use_lmr = !config.use_lmr; }
     if rng.gen_bool(0.03) { config.use_futility_pruning = !config.use_futility_pruning; }
     if rng.gen_bool(0.03) { config.use_delta_pruning = !config.use_delta_pruning; }
+    if rng.gen_bool(0.03) { config.use_qs_checks = !config.use_qs_checks; }
+    if rng.gen_bool(0.03) { config.use_check_extensions = !config.use_check_extensions; }
+    if rng.gen_bool(0.03) { config.use_recapture_extensions = !config.use_recapture_extensions; }
+    if rng.gen_bool(0.03) { config.use_passed_pawn_extensions = !config.use_passed_pawn_extensions; }
+    if rng.gen_bool(0.03) { config.use_singular_extensions = !config.use_singular_extensions; }
+    if rng.gen_bool(0.03) { config.use_reverse_futility_pruning = !config.use_reverse_futility_pruning; }
+    if rng.gen_bool(0.03) { config.use_razoring = !config.use_razoring; }
+    if rng.gen_bool(0.03) { config.use_late_move_pruning = !config.use_late_move_pruning; }
+    if rng.gen_bool(0.03) { config.use_probcut = !config.use_probcut; }
+    if rng.gen_bool(0.03) { config.use_adaptive_nmp = !config.use_adaptive_nmp; }
+    if rng.gen_bool(0.03) { config.use_counter_moves = !config.use_counter_moves; }
+    if rng.gen_bool(0.03) { config.use_continuation_history = !config.use_continuation_history; }
 
     // Mutate numeric values individually
     config.mcts_simulations = mutate_numeric(config.mcts_simulations as i32, rng) as u32;
@@ -1462,6 +1707,38 @@ fn mutate(config: &mut SearchConfig, rng: &mut impl Rng) {
     config.opponent_weakness_weight = mutate_numeric(config.opponent_weakness_weight, rng);
     config.contempt_factor = mutate_numeric(config.contempt_factor, rng);
     config.draw_avoidance_margin = mutate_numeric(config.draw_avoidance_margin, rng);
+    config.reverse_futility_margin = mutate_numeric(config.reverse_futility_margin, rng);
+    config.razoring_margin = mutate_numeric(config.razoring_margin, rng);
+    config.probcut_margin = mutate_numeric(config.probcut_margin, rng);
+
+    // Search constants have bounds, and the small ones move in steps of one.
+    config.late_move_pruning_base = mutate_step(config.late_move_pruning_base, LATE_MOVE_PRUNING_BASE_RANGE, rng);
+    config.nmp_reduction = mutate_step(config.nmp_reduction, NMP_REDUCTION_RANGE, rng);
+    config.nmp_min_depth = mutate_step(config.nmp_min_depth, NMP_MIN_DEPTH_RANGE, rng);
+    config.lmr_min_depth = mutate_step(config.lmr_min_depth, LMR_MIN_DEPTH_RANGE, rng);
+    config.lmr_min_move_index = mutate_step(config.lmr_min_move_index, LMR_MIN_MOVE_INDEX_RANGE, rng);
+    config.mcts_leaf_depth = mutate_step(config.mcts_leaf_depth, MCTS_LEAF_DEPTH_RANGE, rng);
+    config.nmp_eval_margin = mutate_numeric(config.nmp_eval_margin, rng)
+        .clamp(*NMP_EVAL_MARGIN_RANGE.start(), *NMP_EVAL_MARGIN_RANGE.end());
+    config.lmr_divisor = mutate_numeric(config.lmr_divisor, rng)
+        .clamp(*LMR_DIVISOR_RANGE.start(), *LMR_DIVISOR_RANGE.end());
+    config.aspiration_window = mutate_numeric(config.aspiration_window, rng)
+        .clamp(*ASPIRATION_WINDOW_RANGE.start(), *ASPIRATION_WINDOW_RANGE.end());
+    config.mcts_exploration = mutate_numeric(config.mcts_exploration, rng)
+        .clamp(*MCTS_EXPLORATION_RANGE.start(), *MCTS_EXPLORATION_RANGE.end());
+}
+
+/// Mutates a small integer gene by one step up or down, staying within `range`.
+fn mutate_step(value: u8, range: RangeInclusive<u8>, rng: &mut impl Rng) -> u8 {
+    if !rng.gen_bool(MUTATION_CHANCE) {
+        return value;
+    }
+    let stepped = if rng.gen_bool(0.5) {
+        value.saturating_add(1)
+    } else {
+        value.saturating_sub(1)
+    };
+    stepped.clamp(*range.start(), *range.end())
 }
 
 /// Decides if a mutation should occur and, if so, by how much.
@@ -1566,6 +1843,26 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_cache_manager_keeps_one_cache_per_config() {
+        let cache_manager = CacheManager::new(None);
+        let config = SearchConfig::default();
+        let other_config = SearchConfig { contempt_factor: config.contempt_factor + 1, ..config.clone() };
+
+        let first = cache_manager.get_cache_for_config(&config);
+        let second = cache_manager.get_cache_for_config(&config);
+        let other = cache_manager.get_cache_for_config(&other_config);
+        assert!(Arc::ptr_eq(&first.tables.evaluation_cache, &second.tables.evaluation_cache));
+        assert!(!Arc::ptr_eq(&first.tables.evaluation_cache, &other.tables.evaluation_cache));
+
+        drop(other);
+        assert!(!cache_manager.caches.lock().unwrap().contains_key(&other_config));
+        drop(first);
+        assert!(cache_manager.caches.lock().unwrap().contains_key(&config));
+        drop(second);
+        assert!(cache_manager.caches.lock().unwrap().is_empty());
+    }
+
     #[tokio::test]
     async fn test_evolve_population_winner_scenario() {
         let temp_dir = tempdir().unwrap();
@@ -1586,7 +1883,7 @@ mod tests {
             round: NUM_ROUNDS,
             population,
             matches: vec![
-                Match { round: 1, white_player_name: "individual_0.json".to_string(), black_player_name: "individual_1.json".to_string(), status: "completed".to_string(), result: "1-0".to_string(), san: "".to_string() }
+                Match { round: 1, white_player_name: "individual_0.json".to_string(), black_player_name: "individual_1.json".to_string(), status: "completed".to_string(), result: "1-0".to_string(), san: "".to_string(), opening: "".to_string(), termination: None }
             ],
             previous_matchups: HashSet::new(),
             white_games_played: HashMap::new(),
@@ -1596,7 +1893,7 @@ mod tests {
             sts_results: None,
         };
 
-        let evolution_manager = EvolutionManager::new(Arc::new(Mutex::new(false)), Arc::new(Mutex::new(0)));
+        let evolution_manager = EvolutionManager::new(Arc::new(Mutex::new(false)), Arc::new(Mutex::new(0)), None, None, None);
         evolution_manager.evolve_population_swiss(&generation, &next_gen_dir).unwrap();
 
         // Check that the next generation was created
@@ -1628,7 +1925,7 @@ mod tests {
             round: NUM_ROUNDS,
             population,
             matches: vec![
-                 Match { round: 1, white_player_name: "individual_0.json".to_string(), black_player_name: "individual_1.json".to_string(), status: "completed".to_string(), result: "1/2-1/2".to_string(), san: "".to_string() }
+                 Match { round: 1, white_player_name: "individual_0.json".to_string(), black_player_name: "individual_1.json".to_string(), status: "completed".to_string(), result: "1/2-1/2".to_string(), san: "".to_string(), opening: "".to_string(), termination: None }
             ],
             previous_matchups: HashSet::new(),
             white_games_played: HashMap::new(),
@@ -1638,7 +1935,7 @@ mod tests {
             sts_results: None,
         };
 
-        let evolution_manager = EvolutionManager::new(Arc::new(Mutex::new(false)), Arc::new(Mutex::new(0)));
+        let evolution_manager = EvolutionManager::new(Arc::new(Mutex::new(false)), Arc::new(Mutex::new(0)), None, None, None);
         evolution_manager.evolve_population_swiss(&generation, &next_gen_dir).unwrap();
 
         // Check that the next generation was created
@@ -1671,7 +1968,7 @@ mod tests {
             sts_results: None,
         };
 
-        let evolution_manager = EvolutionManager::new(Arc::new(Mutex::new(false)), Arc::new(Mutex::new(0)));
+        let evolution_manager = EvolutionManager::new(Arc::new(Mutex::new(false)), Arc::new(Mutex::new(0)), None, None, None);
 
         // Mock the STS results
         let mut sts_results = vec![];
@@ -1687,6 +1984,7 @@ mod tests {
                 correct_moves: (i * 2), // Make the score proportional to the ID
                 total_positions: 100,
                 elo: Some(1000.0 + (i as f64 * 10.0)),
+                limit: None,
             });
         }
 
@@ -1699,4 +1997,108 @@ mod tests {
         let next_gen_population = Population::load(&next_gen_dir);
         assert_eq!(next_gen_population.individuals.len(), POPULATION_SIZE);
     }
+
+    #[test]
+    fn test_search_genes_stay_in_bounds() {
+        let mut rng = rand::thread_rng();
+        let mut config = SearchConfig::default_with_randomization(&mut rng);
+        let mut algorithms = HashSet::new();
+        for _ in 0..2_000 {
+            let other = SearchConfig::default_with_randomization(&mut rng);
+            config = crossover(&config, &other, &mut rng);
+            mutate(&mut config, &mut rng);
+            algorithms.insert(config.search_algorithm.clone());
+
+            assert!(NMP_REDUCTION_RANGE.contains(&config.nmp_reduction));
+            assert!(NMP_MIN_DEPTH_RANGE.contains(&config.nmp_min_depth));
+            assert!(NMP_EVAL_MARGIN_RANGE.contains(&config.nmp_eval_margin));
+            assert!(LMR_MIN_DEPTH_RANGE.contains(&config.lmr_min_depth));
+            assert!(LMR_MIN_MOVE_INDEX_RANGE.contains(&config.lmr_min_move_index));
+            assert!(LMR_DIVISOR_RANGE.contains(&config.lmr_divisor));
+            assert!(ASPIRATION_WINDOW_RANGE.contains(&config.aspiration_window));
+            assert!(LATE_MOVE_PRUNING_BASE_RANGE.contains(&config.late_move_pruning_base));
+            assert!(MCTS_EXPLORATION_RANGE.contains(&config.mcts_exploration));
+            assert!(MCTS_LEAF_DEPTH_RANGE.contains(&config.mcts_leaf_depth));
+        }
+        assert_eq!(algorithms, HashSet::from(SearchAlgorithm::EVOLVABLE));
+    }
+
+    #[test]
+    fn test_mutate_step_moves_small_genes() {
+        let mut rng = rand::thread_rng();
+        let mut seen = HashSet::new();
+        let mut value = 3;
+        for _ in 0..2_000 {
+            value = mutate_step(value, NMP_REDUCTION_RANGE, &mut rng);
+            seen.insert(value);
+        }
+        assert!(seen.len() > 1);
+        assert!(seen.iter().all(|v| NMP_REDUCTION_RANGE.contains(v)));
+    }
+
+    #[test]
+    fn test_rules_result_applies_draw_rules() {
+        let position = |fen: &str| -> Chess { opening_suite::start_position(fen).unwrap() };
+
+        let mate = position("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1");
+        assert_eq!(rules_result(&mate, 1), Some((GameResult::WhiteWin, Termination::Checkmate)));
+        // Checkmate on the hundredth ply still wins.
+        let late_mate = position("R5k1/5ppp/8/8/8/8/8/6K1 b - - 100 80");
+        assert_eq!(rules_result(&late_mate, 1), Some((GameResult::WhiteWin, Termination::Checkmate)));
+
+        let stalemate = position("7k/5Q2/6K1/8/8/8/8/8 b - - 0 1");
+        assert_eq!(rules_result(&stalemate, 1), Some((GameResult::Draw, Termination::Stalemate)));
+        let bare_kings = position("8/8/4k3/8/8/3NK3/8/8 w - - 0 1");
+        assert_eq!(rules_result(&bare_kings, 1), Some((GameResult::Draw, Termination::InsufficientMaterial)));
+
+        let rook_ending = position("8/8/4k3/8/8/3RK3/8/8 w - - 99 80");
+        assert_eq!(rules_result(&rook_ending, 2), None);
+        assert_eq!(rules_result(&rook_ending, 3), Some((GameResult::Draw, Termination::Repetition)));
+        let rook_ending = position("8/8/4k3/8/8/3RK3/8/8 w - - 100 80");
+        assert_eq!(rules_result(&rook_ending, 1), Some((GameResult::Draw, Termination::FiftyMoves)));
+    }
+
+    #[tokio::test]
+    async fn test_play_game_adjudicates_tablebase_positions() {
+        let tablebases = Arc::new(Tablebases::open("test-data/syzygy").unwrap());
+        let evolution_manager = EvolutionManager::new(Arc::new(Mutex::new(false)), Arc::new(Mutex::new(0)), Some(tablebases.clone()), None, None);
+        let cache_manager = CacheManager::new(Some(tablebases));
+        let config = SearchConfig::default();
+        let (white_guard, black_guard) = (cache_manager.get_cache_for_config(&config), cache_manager.get_cache_for_config(&config));
+
+        for (fen, expected) in [
+            ("8/8/8/3k4/8/8/8/KQ6 w - - 0 1", GameResult::WhiteWin),
+            ("8/8/8/3k4/8/8/8/KQ6 b - - 0 1", GameResult::WhiteWin),
+            ("kr6/8/8/8/3K4/8/8/8 w - - 0 1", GameResult::BlackWin),
+            // Black takes the undefended rook.
+            ("8/8/8/8/8/8/1k6/1R5K b - - 0 1", GameResult::Draw),
+        ] {
+            let (result, termination, san) = evolution_manager.play_game(0, fen, &config, &config, &white_guard, &black_guard).await.unwrap();
+            assert_eq!((result, termination), (expected, Termination::Adjudication), "{fen}");
+            assert!(san.is_empty(), "{fen}");
+        }
+    }
+
+    #[test]
+    fn test_termination_counts_skip_pending_matches() {
+        let game = |termination| Match {
+            round: 1,
+            white_player_name: "individual_0.json".to_string(),
+            black_player_name: "individual_1.json".to_string(),
+            status: "completed".to_string(),
+            result: "1/2-1/2".to_string(),
+            san: String::new(),
+            opening: String::new(),
+            termination,
+        };
+        let matches = [
+            game(Some(Termination::Repetition)),
+            game(None),
+            game(Some(Termination::FiftyMoves)),
+            game(Some(Termination::Repetition)),
+        ];
+        let counts = termination_counts(&matches);
+        assert_eq!(counts.into_iter().collect::<Vec<_>>(), [(Termination::Repetition, 2), (Termination::FiftyMoves, 1)]);
+        assert_eq!(serde_json::to_string(&Termination::FiftyMoves).unwrap(), "\"fifty_moves\"");
+    }
 }
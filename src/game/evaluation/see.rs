@@ -18,6 +18,10 @@ fn get_piece_value(role: Role) -> i32 {
 /// Determines the likely outcome of a series of exchanges on a given square for a given move.
 /// The `attacker_square` is the square of the piece moving to `target_square`.
 /// Returns a score indicating the material gain or loss. A positive score means the exchange is favorable.
+///
+/// Each side may stop the exchange instead of recapturing, so a recapture that would lose
+/// material is not counted. Without this, a defended piece taken by a smaller one always
+/// counted as the full exchange, even when the defender would never take back.
 pub fn see(board: &Board, attacker_square: Square, target_square: Square) -> i32 {
     let attacker_piece = if let Some(p) = board.piece_at(attacker_square) {
         p
@@ -72,6 +76,7 @@ fn see_recursive(board: &Board, target_square: Square, color: Color) -> i32 {
     next_board.set_piece_at(target_square, attacker_piece);
 
     // The value of this part of the exchange is the piece we capture,
-    // minus the value of the opponent's response.
-    get_piece_value(captured_piece.role) - see_recursive(&next_board, target_square, !color)
+    // minus the value of the opponent's response. A side is never forced to
+    // recapture, so a losing continuation is worth nothing.
+    (get_piece_value(captured_piece.role) - see_recursive(&next_board, target_square, !color)).max(0)
 }
@@ -348,3 +348,11 @@ fn test_contempt_factor_not_applied() {
     assert!(score > 850);
     assert_eq!(score, evaluate(&pos, &SearchConfig::default()));
 }
+
+#[test]
+fn test_see_recapture_is_optional() {
+    // Nxd5 wins a pawn: QxN would lose the queen to the rook on d1, so black does not recapture.
+    let fen: Fen = "3q2k1/8/8/3p4/8/2N5/8/3R2K1 w - - 0 1".parse().unwrap();
+    let pos: Chess = fen.into_position(CastlingMode::Standard).unwrap();
+    assert_eq!(see::see(pos.board(), shakmaty::Square::C3, shakmaty::Square::D5), 100);
+}
@@ -1,4 +1,7 @@
 // game/mod.rs
 
 pub mod evaluation;
+pub mod opening_book;
+pub mod opening_suite;
 pub mod search;
+pub mod tablebase;
@@ -0,0 +1,173 @@
+// src/game/opening_book.rs
+
+use clap::ValueEnum;
+use rand::Rng;
+use shakmaty::uci::UciMove;
+use shakmaty::zobrist::{Zobrist64, ZobristHash};
+use shakmaty::{Chess, EnPassantMode, Move, Role, Square};
+use std::fs;
+use std::io;
+use std::path::Path;
+
+/// Size of an entry in a Polyglot book file.
+const ENTRY_BYTES: usize = 16;
+
+/// How a move is chosen when the book has several for a position.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, ValueEnum)]
+pub enum BookSelection {
+    /// Picks a move at random, in proportion to the weights in the book.
+    #[default]
+    Weighted,
+    /// Always picks the move with the highest weight.
+    Best,
+}
+
+/// An entry of a Polyglot book: a move for the position with the given key.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct BookEntry {
+    key: u64,
+    raw_move: u16,
+    weight: u16,
+}
+
+/// A Polyglot opening book.
+///
+/// Positions are looked up by their Polyglot key, which is the Zobrist hash shakmaty computes.
+/// Book moves are only played during the first `max_plies` plies of a game.
+pub struct OpeningBook {
+    /// Sorted by key, as in the file.
+    entries: Vec<BookEntry>,
+    max_plies: usize,
+    selection: BookSelection,
+}
+
+impl OpeningBook {
+    pub fn open(path: impl AsRef<Path>, max_plies: usize, selection: BookSelection) -> io::Result<Self> {
+        Self::from_bytes(&fs::read(path)?, max_plies, selection)
+    }
+
+    /// Parses a Polyglot book: big-endian entries of a 64-bit key, a 16-bit move, a 16-bit
+    /// weight and 32 bits of learning data, which are ignored.
+    pub fn from_bytes(bytes: &[u8], max_plies: usize, selection: BookSelection) -> io::Result<Self> {
+        if !bytes.len().is_multiple_of(ENTRY_BYTES) {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "truncated Polyglot book"));
+        }
+        let mut entries: Vec<BookEntry> = bytes
+            .chunks_exact(ENTRY_BYTES)
+            .map(|chunk| BookEntry {
+                key: u64::from_be_bytes(chunk[0..8].try_into().unwrap()),
+                raw_move: u16::from_be_bytes([chunk[8], chunk[9]]),
+                weight: u16::from_be_bytes([chunk[10], chunk[11]]),
+            })
+            .collect();
+        // Books are sorted already, but lookups rely on it.
+        entries.sort_by_key(|entry| entry.key);
+        Ok(Self { entries, max_plies, selection })
+    }
+
+    pub fn entry_count(&self) -> usize {
+        self.entries.len()
+    }
+
+    /// The legal book moves for `pos` with their weights, in book order.
+    pub fn moves(&self, pos: &Chess) -> Vec<(Move, u16)> {
+        let key = pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0;
+        let start = self.entries.partition_point(|entry| entry.key < key);
+        self.entries[start..]
+            .iter()
+            .take_while(|entry| entry.key == key)
+            .filter_map(|entry| Some((decode_move(entry.raw_move).to_move(pos).ok()?, entry.weight)))
+            .collect()
+    }
+
+    /// The book move to play in `pos`, which is `ply` plies into the game. Moves with a weight
+    /// of zero are never played.
+    pub fn pick_move<R: Rng>(&self, pos: &Chess, ply: usize, rng: &mut R) -> Option<Move> {
+        if ply >= self.max_plies {
+            return None;
+        }
+        let moves: Vec<(Move, u16)> = self.moves(pos).into_iter().filter(|&(_, weight)| weight > 0).collect();
+        match self.selection {
+            BookSelection::Best => {
+                // Unlike `max_by_key`, prefers the first of equally weighted moves.
+                let best = moves.iter().map(|&(_, weight)| weight).max()?;
+                moves.iter().find(|&&(_, weight)| weight == best).map(|&(m, _)| m)
+            }
+            BookSelection::Weighted => {
+                let total: u32 = moves.iter().map(|&(_, weight)| weight as u32).sum();
+                if total == 0 {
+                    return None;
+                }
+                let mut pick = rng.gen_range(0..total);
+                for (m, weight) in moves {
+                    if pick < weight as u32 {
+                        return Some(m);
+                    }
+                    pick -= weight as u32;
+                }
+                None
+            }
+        }
+    }
+}
+
+/// Decodes a Polyglot move: the destination in bits 0-5, the origin in bits 6-11 and the
+/// promotion piece in bits 12-14. Castling is written as the king capturing its rook.
+fn decode_move(raw: u16) -> UciMove {
+    let square = |bits: u16| Square::new((bits & 63) as u32);
+    let promotion = match (raw >> 12) & 7 {
+        1 => Some(Role::Knight),
+        2 => Some(Role::Bishop),
+        3 => Some(Role::Rook),
+        4 => Some(Role::Queen),
+        _ => None,
+    };
+    UciMove::Normal { from: square(raw >> 6), to: square(raw), promotion }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+    use shakmaty::{fen::Fen, CastlingMode, Position};
+
+    const BOOK_PATH: &str = "resources/gm2001.bin";
+
+    #[test]
+    fn test_book_has_weighted_moves_for_start_position() {
+        let book = OpeningBook::open(BOOK_PATH, 8, BookSelection::Best).unwrap();
+        let pos = Chess::default();
+        let moves = book.moves(&pos);
+        assert!(moves.len() > 1);
+        assert!(moves.iter().all(|&(m, _)| pos.is_legal(m)));
+
+        let best = book.pick_move(&pos, 0, &mut StdRng::seed_from_u64(1)).unwrap();
+        let best_weight = moves.iter().map(|&(_, weight)| weight).max().unwrap();
+        assert!(moves.contains(&(best, best_weight)));
+        assert_eq!(book.pick_move(&pos, 8, &mut StdRng::seed_from_u64(1)), None);
+    }
+
+    #[test]
+    fn test_weighted_selection_varies_the_opening() {
+        let book = OpeningBook::open(BOOK_PATH, 8, BookSelection::Weighted).unwrap();
+        let mut rng = StdRng::seed_from_u64(7);
+        let openings: std::collections::HashSet<_> =
+            (0..50).filter_map(|_| book.pick_move(&Chess::default(), 0, &mut rng)).collect();
+        assert!(openings.len() > 1);
+    }
+
+    #[test]
+    fn test_castling_is_decoded_from_king_takes_rook() {
+        let fen: Fen = "r3k2r/8/8/8/8/8/8/R3K2R w KQkq - 0 1".parse().unwrap();
+        let pos: Chess = fen.into_position(CastlingMode::Standard).unwrap();
+        let raw = (Square::E1 as u16) << 6 | Square::H1 as u16;
+        let m = decode_move(raw).to_move(&pos).unwrap();
+        assert_eq!(m, Move::Castle { king: Square::E1, rook: Square::H1 });
+    }
+
+    #[test]
+    fn test_truncated_book_is_rejected() {
+        assert!(OpeningBook::from_bytes(&[0; 20], 8, BookSelection::Best).is_err());
+    }
+}
@@ -0,0 +1,186 @@
+// src/game/opening_suite.rs
+
+use pgn_reader::{RawTag, Reader, SanPlus, Skip, Visitor};
+use rand::seq::SliceRandom;
+use rand::Rng;
+use shakmaty::fen::Fen;
+use shakmaty::{CastlingMode, Chess, EnPassantMode, Position};
+use std::fs;
+use std::io;
+use std::ops::ControlFlow;
+use std::path::Path;
+
+/// A set of start positions for GA games, so that a pairing of two deterministic engines does
+/// not always replay the same game.
+pub struct OpeningSuite {
+    /// The start positions as FENs.
+    openings: Vec<String>,
+    /// Whether each opening is played a second time with the colors reversed.
+    play_reversed: bool,
+}
+
+impl OpeningSuite {
+    /// Loads a suite from an EPD file (`.epd`), or else from a PGN file, whose games end in
+    /// the start positions. Fails if a position is invalid or the suite is empty.
+    pub fn open(path: impl AsRef<Path>, play_reversed: bool) -> io::Result<Self> {
+        let path = path.as_ref();
+        let openings = if path.extension().and_then(|s| s.to_str()) == Some("epd") {
+            parse_epd(&fs::read_to_string(path)?)?
+        } else {
+            parse_pgn(fs::File::open(path)?)?
+        };
+        Self::new(openings, play_reversed)
+    }
+
+    pub fn new(openings: Vec<String>, play_reversed: bool) -> io::Result<Self> {
+        if openings.is_empty() {
+            return Err(io::Error::new(io::ErrorKind::InvalidData, "the opening suite is empty"));
+        }
+        Ok(Self { openings, play_reversed })
+    }
+
+    pub fn opening_count(&self) -> usize {
+        self.openings.len()
+    }
+
+    pub fn play_reversed(&self) -> bool {
+        self.play_reversed
+    }
+
+    /// A random start position of the suite, as a FEN.
+    pub fn choose<R: Rng>(&self, rng: &mut R) -> &str {
+        self.openings.choose(rng).expect("the opening suite is not empty")
+    }
+}
+
+/// Parses the start position of a game, as stored in `Match::opening`. An empty string is the
+/// standard start position.
+pub fn start_position(fen: &str) -> Result<Chess, String> {
+    if fen.is_empty() {
+        return Ok(Chess::default());
+    }
+    let fen: Fen = fen.parse().map_err(|e| format!("Invalid FEN {fen}: {e}"))?;
+    fen.into_position(CastlingMode::Standard).map_err(|e| format!("Illegal position: {e}"))
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+fn to_fen(pos: &Chess) -> String {
+    Fen::from_position(pos, EnPassantMode::Legal).to_string()
+}
+
+/// Reads the positions of an EPD file. Only the four position fields of each line are used,
+/// any operations after them are ignored.
+fn parse_epd(content: &str) -> io::Result<Vec<String>> {
+    let mut openings = Vec::new();
+    for line in content.lines() {
+        let fields: Vec<&str> = line.split_whitespace().take(4).collect();
+        if fields.is_empty() {
+            continue;
+        }
+        if fields.len() < 4 {
+            return Err(invalid_data(format!("Incomplete EPD line: {line}")));
+        }
+        let pos = start_position(&format!("{} 0 1", fields.join(" "))).map_err(invalid_data)?;
+        openings.push(to_fen(&pos));
+    }
+    Ok(openings)
+}
+
+/// Reads the final positions of the main lines of a PGN file. Games may start from a `FEN` tag.
+fn parse_pgn(reader: impl io::Read) -> io::Result<Vec<String>> {
+    let mut reader = Reader::new(reader);
+    let mut openings = Vec::new();
+    while let Some(result) = reader.read_game(&mut OpeningLine)? {
+        openings.push(to_fen(&result.map_err(invalid_data)?));
+    }
+    Ok(openings)
+}
+
+/// Plays the main line of a PGN game.
+struct OpeningLine;
+
+impl Visitor for OpeningLine {
+    type Tags = Option<Chess>;
+    type Movetext = Chess;
+    type Output = Result<Chess, String>;
+
+    fn begin_tags(&mut self) -> ControlFlow<Self::Output, Self::Tags> {
+        ControlFlow::Continue(None)
+    }
+
+    fn tag(&mut self, tags: &mut Self::Tags, name: &[u8], value: RawTag<'_>) -> ControlFlow<Self::Output> {
+        if name == b"FEN" {
+            match start_position(&value.decode_utf8_lossy()) {
+                Ok(pos) => *tags = Some(pos),
+                Err(err) => return ControlFlow::Break(Err(err)),
+            }
+        }
+        ControlFlow::Continue(())
+    }
+
+    fn begin_movetext(&mut self, tags: Self::Tags) -> ControlFlow<Self::Output, Self::Movetext> {
+        ControlFlow::Continue(tags.unwrap_or_default())
+    }
+
+    fn begin_variation(&mut self, _movetext: &mut Self::Movetext) -> ControlFlow<Self::Output, Skip> {
+        ControlFlow::Continue(Skip(true))
+    }
+
+    fn san(&mut self, movetext: &mut Self::Movetext, san_plus: SanPlus) -> ControlFlow<Self::Output> {
+        match san_plus.san.to_move(movetext) {
+            Ok(m) => {
+                movetext.play_unchecked(m);
+                ControlFlow::Continue(())
+            }
+            Err(err) => ControlFlow::Break(Err(format!("Illegal move {san_plus}: {err}"))),
+        }
+    }
+
+    fn end_game(&mut self, movetext: Self::Movetext) -> Self::Output {
+        Ok(movetext)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_epd_positions_are_read_without_operations() {
+        let epd = "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - id \"e4\";\n\n\
+                   rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - bm Nf3;\n";
+        let openings = parse_epd(epd).unwrap();
+        assert_eq!(
+            openings,
+            [
+                "rnbqkbnr/pppppppp/8/8/4P3/8/PPPP1PPP/RNBQKBNR b KQkq - 0 1",
+                "rnbqkbnr/pppp1ppp/8/4p3/4P3/8/PPPP1PPP/RNBQKBNR w KQkq - 0 1",
+            ]
+        );
+        assert!(parse_epd("rnbqkbnr/pppppppp/8/8/8/8/PPPPPPPP/RNBQKBNR w\n").is_err());
+    }
+
+    #[test]
+    fn test_pgn_games_end_in_their_start_positions() {
+        let pgn = "[Event \"Sicilian\"]\n\n1. e4 c5 (1... e5 2. Nf3) 2. Nf3 *\n\n\
+                   [FEN \"4k3/8/8/8/8/8/4P3/4K3 w - - 0 1\"]\n\n1. e4 *\n";
+        let openings = parse_pgn(pgn.as_bytes()).unwrap();
+        assert_eq!(
+            openings,
+            [
+                "rnbqkbnr/pp1ppppp/8/2p5/4P3/5N2/PPPP1PPP/RNBQKB1R b KQkq - 1 2",
+                "4k3/8/8/8/4P3/8/8/4K3 b - - 0 1",
+            ]
+        );
+        assert!(parse_pgn("1. e4 e4 *\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_empty_suite_is_rejected() {
+        assert!(OpeningSuite::new(Vec::new(), false).is_err());
+        assert_eq!(start_position("").unwrap(), Chess::default());
+    }
+}
@@ -3,13 +3,25 @@
 pub mod mcts;
 pub mod evaluation_cache;
 pub mod mcts_cache;
+pub mod transposition_table;
+pub mod history;
+pub mod hash_table;
 
-use shakmaty::{Chess, Move, Position, Piece, san::SanPlus, EnPassantMode};
-use shakmaty::zobrist::ZobristHash;
+use shakmaty::{Chess, Color, Move, Position, Role, san::SanPlus, EnPassantMode};
+use shakmaty::zobrist::{Zobrist64, ZobristHash};
 use crate::game::evaluation;
+use crate::game::tablebase::Tablebases;
 use evaluation_cache::EvaluationCache;
 pub use mcts_cache::{MctsCache, MctsNodeData};
-use crate::constants::MATE_SCORE;
+use transposition_table::{Bound, TranspositionTable, TtEntry, score_from_tt, score_to_tt};
+use history::MoveHistory;
+use crate::constants::{
+    ASPIRATION_WINDOW_RANGE, LATE_MOVE_PRUNING_BASE_RANGE, LMR_DIVISOR_RANGE, LMR_MIN_DEPTH_RANGE,
+    LMR_MIN_MOVE_INDEX_RANGE, MATE_SCORE, MATE_THRESHOLD, MAX_PLY, MAX_WIN_PROBABILITY_CP, MCTS_EXPLORATION_RANGE,
+    MCTS_LEAF_DEPTH_RANGE, MIN_TABLE_MB, NMP_EVAL_MARGIN_RANGE, NMP_MIN_DEPTH_RANGE, NMP_REDUCTION_RANGE, TB_WIN_SCORE,
+    WIN_PROBABILITY_SCALE,
+};
+use shakmaty_syzygy::Wdl;
 
 use serde::{Deserialize, Serialize};
 
@@ -17,9 +29,17 @@ use serde::{Deserialize, Serialize};
 pub enum SearchAlgorithm {
     Pvs,
     Mcts,
+    /// MCTS whose leaves are valued by a shallow alpha-beta search.
+    MctsAb,
+}
+
+impl SearchAlgorithm {
+    /// The algorithms individuals are created with and mutate between.
+    pub const EVOLVABLE: [SearchAlgorithm; 3] = [SearchAlgorithm::Pvs, SearchAlgorithm::Mcts, SearchAlgorithm::MctsAb];
 }
 
 #[derive(Clone, Debug, Serialize, Deserialize, PartialEq, Eq, Hash)]
+#[serde(default)] // Genes added later fall back to their defaults for saved individuals
 pub struct SearchConfig {
     pub search_depth: u8,
     pub search_algorithm: SearchAlgorithm,
@@ -33,6 +53,18 @@ pub struct SearchConfig {
     pub use_lmr: bool,
     pub use_futility_pruning: bool,
     pub use_delta_pruning: bool,
+    pub use_qs_checks: bool,
+    pub use_check_extensions: bool,
+    pub use_recapture_extensions: bool,
+    pub use_passed_pawn_extensions: bool,
+    pub use_singular_extensions: bool,
+    pub use_reverse_futility_pruning: bool,
+    pub use_razoring: bool,
+    pub use_late_move_pruning: bool,
+    pub use_probcut: bool,
+    pub use_adaptive_nmp: bool,
+    pub use_counter_moves: bool,
+    pub use_continuation_history: bool,
     pub pawn_structure_weight: i32,
     pub piece_mobility_weight: i32,
     pub king_safety_weight: i32,
@@ -59,6 +91,19 @@ pub struct SearchConfig {
     pub opponent_weakness_weight: i32,
     pub contempt_factor: i32,
     pub draw_avoidance_margin: i32,
+    pub reverse_futility_margin: i32, // Per ply of remaining depth
+    pub razoring_margin: i32,         // Per ply of remaining depth
+    pub late_move_pruning_base: u8,   // Quiet moves searched at depth d: base + d * d
+    pub probcut_margin: i32,
+    pub nmp_reduction: u8,
+    pub nmp_min_depth: u8,
+    pub nmp_eval_margin: i32, // Adaptive NMP reduces one more ply per margin the eval beats beta
+    pub lmr_min_depth: u8,
+    pub lmr_min_move_index: u8,
+    pub lmr_divisor: i32, // In hundredths: reduction = 1 + ln(depth) * ln(index) / divisor
+    pub aspiration_window: i32,
+    pub mcts_exploration: i32, // In hundredths: the PUCT constant weighing priors against results
+    pub mcts_leaf_depth: u8,   // Depth of the alpha-beta search at the leaves of the MCTS-AB hybrid
 }
 
 impl SearchConfig {
@@ -79,9 +124,21 @@ impl SearchConfig {
         config.use_lmr = rng.gen_bool(0.5);
         config.use_futility_pruning = rng.gen_bool(0.5);
         config.use_delta_pruning = rng.gen_bool(0.5);
+        config.use_qs_checks = rng.gen_bool(0.5);
+        config.use_check_extensions = rng.gen_bool(0.5);
+        config.use_recapture_extensions = rng.gen_bool(0.5);
+        config.use_passed_pawn_extensions = rng.gen_bool(0.5);
+        config.use_singular_extensions = rng.gen_bool(0.5);
+        config.use_reverse_futility_pruning = rng.gen_bool(0.5);
+        config.use_razoring = rng.gen_bool(0.5);
+        config.use_late_move_pruning = rng.gen_bool(0.5);
+        config.use_probcut = rng.gen_bool(0.5);
+        config.use_adaptive_nmp = rng.gen_bool(0.5);
+        config.use_counter_moves = rng.gen_bool(0.5);
+        config.use_continuation_history = rng.gen_bool(0.5);
 
         // Randomize enum
-        config.search_algorithm = SearchAlgorithm::Pvs;
+        config.search_algorithm = SearchAlgorithm::EVOLVABLE[rng.gen_range(0..SearchAlgorithm::EVOLVABLE.len())].clone();
 
         // Helper function for numeric randomization
         let mut vary_numeric = |value: i32| -> i32 {
@@ -115,8 +172,25 @@ impl SearchConfig {
         config.enhanced_king_attack_weight = vary_numeric(default_config.enhanced_king_attack_weight);
         config.advanced_passed_pawn_weight = vary_numeric(default_config.advanced_passed_pawn_weight);
         config.opponent_weakness_weight = vary_numeric(default_config.opponent_weakness_weight);
+        config.reverse_futility_margin = vary_numeric(default_config.reverse_futility_margin);
+        config.razoring_margin = vary_numeric(default_config.razoring_margin);
+        config.probcut_margin = vary_numeric(default_config.probcut_margin);
+        config.nmp_eval_margin = vary_numeric(default_config.nmp_eval_margin)
+            .clamp(*NMP_EVAL_MARGIN_RANGE.start(), *NMP_EVAL_MARGIN_RANGE.end());
+        config.lmr_divisor = vary_numeric(default_config.lmr_divisor)
+            .clamp(*LMR_DIVISOR_RANGE.start(), *LMR_DIVISOR_RANGE.end());
+        config.aspiration_window = vary_numeric(default_config.aspiration_window)
+            .clamp(*ASPIRATION_WINDOW_RANGE.start(), *ASPIRATION_WINDOW_RANGE.end());
+        config.mcts_exploration = vary_numeric(default_config.mcts_exploration)
+            .clamp(*MCTS_EXPLORATION_RANGE.start(), *MCTS_EXPLORATION_RANGE.end());
         config.contempt_factor = rng.gen_range(0..=50);
         config.draw_avoidance_margin = rng.gen_range(0..=100);
+        config.late_move_pruning_base = rng.gen_range(LATE_MOVE_PRUNING_BASE_RANGE);
+        config.nmp_reduction = rng.gen_range(NMP_REDUCTION_RANGE);
+        config.nmp_min_depth = rng.gen_range(NMP_MIN_DEPTH_RANGE);
+        config.lmr_min_depth = rng.gen_range(LMR_MIN_DEPTH_RANGE);
+        config.lmr_min_move_index = rng.gen_range(LMR_MIN_MOVE_INDEX_RANGE);
+        config.mcts_leaf_depth = rng.gen_range(MCTS_LEAF_DEPTH_RANGE);
 
         config
     }
@@ -137,6 +211,18 @@ impl Default for SearchConfig {
             use_lmr: true,
             use_futility_pruning: true,
             use_delta_pruning: false,
+            use_qs_checks: false,
+            use_check_extensions: false,
+            use_recapture_extensions: false,
+            use_passed_pawn_extensions: false,
+            use_singular_extensions: false,
+            use_reverse_futility_pruning: false,
+            use_razoring: false,
+            use_late_move_pruning: false,
+            use_probcut: false,
+            use_adaptive_nmp: false,
+            use_counter_moves: false,
+            use_continuation_history: false,
             pawn_structure_weight: 100,
             piece_mobility_weight: 100,
             king_safety_weight: 100,
@@ -163,73 +249,526 @@ impl Default for SearchConfig {
             opponent_weakness_weight: 100,
             contempt_factor: 0,
             draw_avoidance_margin: 0,
+            reverse_futility_margin: 80,
+            razoring_margin: 300,
+            late_move_pruning_base: 3,
+            probcut_margin: 200,
+            nmp_reduction: 3,
+            nmp_min_depth: 3,
+            nmp_eval_margin: 200,
+            lmr_min_depth: 3,
+            lmr_min_move_index: 2,
+            lmr_divisor: 200,
+            aspiration_window: 50,
+            mcts_exploration: 150,
+            mcts_leaf_depth: 2,
         }
     }
 }
 
-use std::sync::{Arc, Mutex};
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::{Duration, Instant};
+
+/// Bounds how much work a single search may do.
+///
+/// The search deepens iteratively up to `max_depth` and stops early once the move time,
+/// the node budget or the hard deadline is exhausted, whichever comes first. Setting the
+/// `stop` flag cancels a running search, which then returns the best move found so far.
+///
+/// With more than one thread, the PVS searcher runs a Lazy SMP search: helper threads search
+/// the same position and share the transposition table, and the calling thread reports the
+/// result. The node budget only counts the calling thread's nodes.
+#[derive(Clone, Debug)]
+pub struct SearchLimits {
+    pub max_depth: u8,
+    pub movetime: Option<Duration>,
+    pub nodes: Option<u64>,
+    pub deadline: Option<Instant>,
+    pub stop: Option<Arc<AtomicBool>>,
+    pub threads: usize,
+}
+
+impl SearchLimits {
+    /// Limits that only bound the search depth.
+    pub fn depth(max_depth: u8) -> Self {
+        Self {
+            max_depth,
+            movetime: None,
+            nodes: None,
+            deadline: None,
+            stop: None,
+            threads: 1,
+        }
+    }
+
+    /// Whether the caller has asked the search to stop.
+    pub fn is_stopped(&self) -> bool {
+        self.stop.as_ref().is_some_and(|stop| stop.load(Ordering::Relaxed))
+    }
+
+    /// The instant after which the search must stop, if any.
+    pub fn hard_deadline(&self, start: Instant) -> Option<Instant> {
+        let movetime_deadline = self.movetime.map(|t| start + t);
+        match (movetime_deadline, self.deadline) {
+            (Some(a), Some(b)) => Some(a.min(b)),
+            (a, b) => a.or(b),
+        }
+    }
+}
 
 #[derive(Clone, Debug)]
 pub struct MoveTreeNode {
     pub move_san: String,
-    pub score: i32,
+    /// For the side that played `move_san`, or the side to move at the root.
+    pub score: Score,
     pub children: Vec<MoveTreeNode>,
 }
 
+/// A search score from the perspective of the side to move.
+///
+/// Every searcher reports its scores on this scale, and MCTS converts between it and the
+/// expected results of its tree with `win_probability` and `from_win_probability`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Score {
+    /// A score in centipawns.
+    Cp(i32),
+    /// Mate in the given number of moves. Negative if the side to move is getting mated.
+    Mate(i32),
+}
+
+impl Default for Score {
+    fn default() -> Self {
+        Score::Cp(0)
+    }
+}
+
+impl Score {
+    /// Converts a raw search score, where mates are encoded as `MATE_SCORE` minus the
+    /// distance in plies, into a `Score`. Other scores are capped at `MAX_WIN_PROBABILITY_CP`,
+    /// like other certain results. This keeps tablebase wins, which carry no mate distance,
+    /// on the centipawn scale.
+    pub fn from_search(score: i32) -> Self {
+        if score >= MATE_THRESHOLD {
+            let plies = MATE_SCORE - score;
+            Score::Mate((plies + 1) / 2)
+        } else if score <= -MATE_THRESHOLD {
+            let plies = MATE_SCORE + score;
+            Score::Mate(-(plies / 2))
+        } else {
+            Score::Cp(score.clamp(-MAX_WIN_PROBABILITY_CP, MAX_WIN_PROBABILITY_CP))
+        }
+    }
+
+    /// The score on the raw centipawn scale used inside the search.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn centipawns(self) -> i32 {
+        match self {
+            Score::Cp(cp) => cp,
+            Score::Mate(moves) if moves > 0 => MATE_SCORE - (2 * moves - 1),
+            Score::Mate(moves) => -MATE_SCORE - 2 * moves,
+        }
+    }
+
+    /// The score for the opponent once the side to move has played the move this score was
+    /// found for. Mates come one ply closer.
+    pub fn after_move(self) -> Self {
+        match self {
+            Score::Cp(cp) => Score::Cp(-cp),
+            Score::Mate(_) => {
+                let score = self.centipawns();
+                Score::from_search(-score - score.signum())
+            }
+        }
+    }
+
+    /// The expected result for the side to move, between 0 and 1, on the logistic curve
+    /// `1 / (1 + exp(-cp / 400))`. A mate is a certain result.
+    pub fn win_probability(self) -> f64 {
+        match self {
+            Score::Cp(cp) => 1.0 / (1.0 + (-(cp as f64) / WIN_PROBABILITY_SCALE).exp()),
+            Score::Mate(moves) if moves > 0 => 1.0,
+            Score::Mate(_) => 0.0,
+        }
+    }
+
+    /// The centipawn score whose win probability is `p`, the inverse of `win_probability`.
+    /// Certain results are capped at `MAX_WIN_PROBABILITY_CP`, since only a search proves mates.
+    pub fn from_win_probability(p: f64) -> Self {
+        let cp = -WIN_PROBABILITY_SCALE * (1.0 / p - 1.0).ln();
+        Score::Cp(cp.round().clamp(-MAX_WIN_PROBABILITY_CP as f64, MAX_WIN_PROBABILITY_CP as f64) as i32)
+    }
+}
+
+impl std::fmt::Display for Score {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Score::Cp(cp) => write!(f, "{cp}cp"),
+            Score::Mate(moves) => write!(f, "#{moves}"),
+        }
+    }
+}
+
+/// Counters collected while searching.
+#[derive(Clone, Debug, Default)]
+pub struct SearchStats {
+    /// Depth of the last completed iteration (or the tree depth for MCTS).
+    pub depth: u8,
+    /// Deepest ply reached, including quiescence search.
+    pub seldepth: u8,
+    /// All nodes visited, including quiescence nodes.
+    pub nodes: u64,
+    /// Nodes visited in quiescence search.
+    pub qnodes: u64,
+    pub tt_hits: u64,
+    pub beta_cutoffs: u64,
+    /// Beta cutoffs produced by the first move searched.
+    pub first_move_cutoffs: u64,
+    /// Root searches repeated because the score fell outside the aspiration window.
+    pub aspiration_researches: u64,
+    /// Positions resolved by a tablebase probe, including filtering the root moves.
+    pub tb_hits: u64,
+    pub elapsed: Duration,
+}
+
+impl SearchStats {
+    /// The share of beta cutoffs caused by the first move, a measure of move ordering quality.
+    pub fn first_move_cutoff_rate(&self) -> f64 {
+        if self.beta_cutoffs == 0 {
+            0.0
+        } else {
+            self.first_move_cutoffs as f64 / self.beta_cutoffs as f64
+        }
+    }
+
+    /// Adds the work done by a Lazy SMP helper thread.
+    fn add_helper(&mut self, helper: &SearchStats) {
+        self.nodes += helper.nodes;
+        self.qnodes += helper.qnodes;
+        self.tt_hits += helper.tt_hits;
+        self.beta_cutoffs += helper.beta_cutoffs;
+        self.first_move_cutoffs += helper.first_move_cutoffs;
+        self.aspiration_researches += helper.aspiration_researches;
+        self.tb_hits += helper.tb_hits;
+    }
+}
+
+impl std::fmt::Display for SearchStats {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Depth={}/{}, Nodes={}, QNodes={}, TT Hits={}, Beta Cutoffs={} ({:.1}% first move), Re-searches={}, TB Hits={}, Time={}ms",
+            self.depth,
+            self.seldepth,
+            self.nodes,
+            self.qnodes,
+            self.tt_hits,
+            self.beta_cutoffs,
+            self.first_move_cutoff_rate() * 100.0,
+            self.aspiration_researches,
+            self.tb_hits,
+            self.elapsed.as_millis()
+        )
+    }
+}
+
+/// The outcome of a search.
+#[derive(Clone, Debug, Default)]
+#[allow(dead_code)] // The tree is only consumed by debugging front-ends for now.
+pub struct SearchResult {
+    pub best_move: Option<Move>,
+    pub score: Score,
+    /// The principal variation, starting with `best_move`.
+    pub pv: Vec<Move>,
+    pub tree: Option<MoveTreeNode>,
+    pub stats: SearchStats,
+}
+
+impl SearchResult {
+    /// The principal variation in SAN, starting from `pos`.
+    pub fn pv_san(&self, pos: &Chess) -> Vec<String> {
+        let mut pos = pos.clone();
+        let mut sans = Vec::with_capacity(self.pv.len());
+        for &m in &self.pv {
+            if !pos.is_legal(m) {
+                break;
+            }
+            sans.push(SanPlus::from_move_and_play_unchecked(&mut pos, m).to_string());
+        }
+        sans
+    }
+
+    /// The principal variation in UCI notation.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn pv_uci(&self) -> Vec<String> {
+        self.pv
+            .iter()
+            .map(|m| m.to_uci(shakmaty::CastlingMode::Standard).to_string())
+            .collect()
+    }
+}
+
 #[cfg_attr(test, allow(dead_code))]
 pub trait Searcher: Send {
+    /// Searches `pos`. `history` holds the hashes of the positions that led to it, oldest
+    /// first, so that repetitions of earlier game positions are recognised as draws.
     fn search(
         &mut self,
         pos: &Chess,
-        depth: u8,
+        history: &[Zobrist64],
+        limits: &SearchLimits,
         config: &SearchConfig,
         build_tree: bool,
         verbose: bool,
-    ) -> (Option<Move>, i32, Option<MoveTreeNode>, Option<String>);
+    ) -> SearchResult;
+}
+
+/// The tables a searcher works with. The entries of the hash tables depend on the evaluation
+/// weights, so they are shared between searchers of the same configuration only. The endgame
+/// tablebases can be shared by all searchers.
+#[derive(Clone)]
+pub struct SearchTables {
+    pub evaluation_cache: Arc<EvaluationCache>,
+    pub transposition_table: Arc<TranspositionTable>,
+    pub tablebases: Option<Arc<Tablebases>>,
+    /// The visit statistics of MCTS searchers. Searchers without one allocate their own.
+    pub mcts_cache: Option<Arc<MctsCache>>,
+}
+
+impl Default for SearchTables {
+    fn default() -> Self {
+        Self {
+            evaluation_cache: Arc::new(EvaluationCache::new()),
+            transposition_table: Arc::new(TranspositionTable::new()),
+            tablebases: None,
+            mcts_cache: None,
+        }
+    }
+}
+
+impl SearchTables {
+    /// Splits `size_mb` between the tables, giving the transposition table three quarters.
+    /// Each table gets at least `MIN_TABLE_MB`, so small budgets do not leave a table with a
+    /// single bucket.
+    pub fn with_size_mb(size_mb: usize) -> Self {
+        let evaluation_mb = (size_mb / 4).max(MIN_TABLE_MB);
+        let transposition_mb = size_mb.saturating_sub(evaluation_mb).max(MIN_TABLE_MB);
+        Self {
+            evaluation_cache: Arc::new(EvaluationCache::with_size_mb(evaluation_mb)),
+            transposition_table: Arc::new(TranspositionTable::with_size_mb(transposition_mb)),
+            tablebases: None,
+            mcts_cache: None,
+        }
+    }
+
+    /// The tables of a searcher running `algorithm`, within `size_mb`. MCTS searchers give a
+    /// quarter of it to their cache of visit statistics.
+    pub fn for_algorithm(algorithm: &SearchAlgorithm, size_mb: usize) -> Self {
+        match algorithm {
+            SearchAlgorithm::Pvs => Self::with_size_mb(size_mb),
+            SearchAlgorithm::Mcts | SearchAlgorithm::MctsAb => {
+                let mcts_mb = (size_mb / 4).max(MIN_TABLE_MB);
+                Self {
+                    mcts_cache: Some(Arc::new(MctsCache::with_size_mb(mcts_mb))),
+                    ..Self::with_size_mb(size_mb.saturating_sub(mcts_mb))
+                }
+            }
+        }
+    }
 }
 
 #[derive(Clone)]
 pub struct PvsSearcher {
-    history_table: [[i32; 64]; 12],
-    killer_moves: [[Option<Move>; 2]; 64],
-    evaluation_cache: Arc<Mutex<EvaluationCache>>,
+    history: MoveHistory,
+    killer_moves: [[Option<Move>; 2]; MAX_PLY],
+    pv_table: Vec<Vec<Move>>,
+    played_moves: Vec<Option<Move>>,
+    tables: SearchTables,
+    stats: SearchStats,
+    node_limit: Option<u64>,
+    deadline: Option<Instant>,
+    stop: Option<Arc<AtomicBool>>,
+    completed_depth: u8,
+    aborted: bool,
+    root_color: Color,
+    game_history: Vec<u64>,
+    search_path: Vec<u64>,
+    /// The root moves that keep the tablebase result, if the root position is in the tables.
+    tb_root_moves: Option<Vec<Move>>,
 }
 
 impl Searcher for PvsSearcher {
     fn search(
+        &mut self,
+        pos: &Chess,
+        history: &[Zobrist64],
+        limits: &SearchLimits,
+        config: &SearchConfig,
+        build_tree: bool,
+        verbose: bool,
+    ) -> SearchResult {
+        self.root_color = pos.turn();
+        self.game_history = history.iter().map(|hash| hash.0).collect();
+        self.history.age();
+        self.tables.transposition_table.new_search();
+        self.tb_root_moves = self.tables.tablebases.as_ref().and_then(|tb| tb.root_moves(pos));
+
+        let helper_count = limits.threads.saturating_sub(1);
+        if helper_count == 0 {
+            return self.iterative_deepening(pos, limits, config, 1, build_tree, verbose);
+        }
+
+        // Helpers run until the main thread is done, so they only need a stop flag of their own.
+        let helper_stop = Arc::new(AtomicBool::new(false));
+        let helper_limits = SearchLimits {
+            nodes: None,
+            stop: Some(helper_stop.clone()),
+            threads: 1,
+            ..limits.clone()
+        };
+        let mut helpers: Vec<PvsSearcher> = (0..helper_count).map(|_| self.clone()).collect();
+        std::thread::scope(|scope| {
+            let handles: Vec<_> = helpers
+                .iter_mut()
+                .enumerate()
+                .map(|(i, helper)| {
+                    let helper_limits = &helper_limits;
+                    // Starting every other helper one ply deeper spreads the threads over
+                    // different depths, so they fill the shared table with different entries.
+                    let first_depth = 1 + (i % 2) as u8;
+                    scope.spawn(move || helper.iterative_deepening(pos, helper_limits, config, first_depth, false, false))
+                })
+                .collect();
+
+            let mut result = self.iterative_deepening(pos, limits, config, 1, build_tree, verbose);
+            helper_stop.store(true, Ordering::Relaxed);
+            for handle in handles {
+                if let Ok(helper_result) = handle.join() {
+                    result.stats.add_helper(&helper_result.stats);
+                }
+            }
+            result
+        })
+    }
+}
+
+impl PvsSearcher {
+    fn iterative_deepening(
+        &mut self,
+        pos: &Chess,
+        limits: &SearchLimits,
+        config: &SearchConfig,
+        first_depth: u8,
+        build_tree: bool,
+        verbose: bool,
+    ) -> SearchResult {
+        let start = Instant::now();
+        self.stats = SearchStats::default();
+        self.node_limit = limits.nodes;
+        self.deadline = limits.hard_deadline(start);
+        self.stop = limits.stop.clone();
+        self.completed_depth = 0;
+        self.aborted = false;
+
+        let mut result = (None, 0, None, Vec::new());
+        let mut previous_score = None;
+        for depth in first_depth.min(limits.max_depth).max(1)..=limits.max_depth.max(1) {
+            let (best_move, score, tree) = self.search_iteration(pos, depth, previous_score, config, build_tree, verbose);
+            if self.aborted {
+                // The interrupted iteration is incomplete, so keep the previous result. If the
+                // search was stopped before any iteration finished, fall back to the best root
+                // move that was fully searched.
+                if result.0.is_none() {
+                    result = (best_move, score, Some(tree), self.pv_table[0].clone());
+                }
+                break;
+            }
+            result = (best_move, score, Some(tree), self.pv_table[0].clone());
+            self.completed_depth = depth;
+            previous_score = Some(score);
+
+            if verbose {
+                let pv: Vec<String> = result.3.iter().map(|m| m.to_string()).collect();
+                println!(
+                    "[id] depth {depth} score {score} nodes {} time {}ms pv {}",
+                    self.stats.nodes,
+                    start.elapsed().as_millis(),
+                    pv.join(" ")
+                );
+            }
+
+            // Another iteration takes longer than everything searched so far, so do not
+            // start one that cannot finish before the move time runs out.
+            if let Some(movetime) = limits.movetime {
+                if start.elapsed() * 2 >= movetime {
+                    break;
+                }
+            }
+        }
+
+        let (best_move, score, tree, pv) = result;
+        self.stats.depth = self.completed_depth;
+        self.stats.elapsed = start.elapsed();
+        SearchResult {
+            best_move,
+            score: Score::from_search(score),
+            pv,
+            tree,
+            stats: std::mem::take(&mut self.stats),
+        }
+    }
+}
+
+impl PvsSearcher {
+    /// Searches the root to `depth`. With aspiration windows, the window is centred on the
+    /// previous iteration's score. A fail-low or fail-high doubles the window on the failing
+    /// side only and repeats the search, until the score falls inside it.
+    fn search_iteration(
         &mut self,
         pos: &Chess,
         depth: u8,
+        previous_score: Option<i32>,
         config: &SearchConfig,
         build_tree: bool,
         verbose: bool,
-    ) -> (Option<Move>, i32, Option<MoveTreeNode>, Option<String>) {
-        if !config.use_aspiration_windows {
+    ) -> (Option<Move>, i32, MoveTreeNode) {
+        // Beyond this the window is opened fully on the failing side.
+        const ASPIRATION_MAX_DELTA: i32 = 1_000;
+
+        let center = previous_score.filter(|score| config.use_aspiration_windows && score.abs() < MATE_THRESHOLD);
+        let Some(center) = center else {
             let args = PvsRootSearchArgs {
                 pos, depth, config, alpha: -MATE_SCORE, beta: MATE_SCORE, build_tree, verbose
             };
-            let (move_opt, score, tree) = self.pvs_root_search(args);
-            return (move_opt, score, Some(tree), None);
-        }
-
-        const ASPIRATION_WINDOW_DELTA: i32 = 50;
-        let score_guess = self.evaluate_with_cache(pos, config);
-        let alpha = score_guess - ASPIRATION_WINDOW_DELTA;
-        let beta = score_guess + ASPIRATION_WINDOW_DELTA;
-
-        let args = PvsRootSearchArgs {
-            pos, depth, config, alpha, beta, build_tree, verbose
+            return self.pvs_root_search(args);
         };
-        let (mut best_move, mut score, mut tree) = self.pvs_root_search(args);
 
-        if score <= alpha || score >= beta {
+        let mut alpha_delta = config.aspiration_window.max(1);
+        let mut beta_delta = alpha_delta;
+        loop {
+            let alpha = if alpha_delta > ASPIRATION_MAX_DELTA { -MATE_SCORE } else { center - alpha_delta };
+            let beta = if beta_delta > ASPIRATION_MAX_DELTA { MATE_SCORE } else { center + beta_delta };
             let args = PvsRootSearchArgs {
-                pos, depth, config, alpha: -MATE_SCORE, beta: MATE_SCORE, build_tree, verbose
+                pos, depth, config, alpha, beta, build_tree, verbose
             };
-            (best_move, score, tree) = self.pvs_root_search(args);
-        }
+            let (best_move, score, tree) = self.pvs_root_search(args);
+            if self.aborted {
+                return (best_move, score, tree);
+            }
 
-        (best_move, score, Some(tree), None)
+            if score <= alpha && alpha > -MATE_SCORE {
+                alpha_delta *= 2;
+            } else if score >= beta && beta < MATE_SCORE {
+                beta_delta *= 2;
+            } else {
+                return (best_move, score, tree);
+            }
+            self.stats.aspiration_researches += 1;
+            if verbose {
+                println!("[aspiration] depth {depth} score {score} outside ({alpha}, {beta}), re-searching");
+            }
+        }
     }
 }
 
@@ -256,12 +795,114 @@ struct PvsSearchParams<'a> {
 
 impl PvsSearcher {
     #[cfg_attr(test, allow(dead_code))]
-    pub fn with_shared_cache(cache: Arc<Mutex<EvaluationCache>>) -> Self {
+    pub fn with_tables(tables: SearchTables) -> Self {
         Self {
-            history_table: [[0; 64]; 12],
-            killer_moves: [[None; 2]; 64],
-            evaluation_cache: cache,
+            history: MoveHistory::new(),
+            killer_moves: [[None; 2]; MAX_PLY],
+            pv_table: vec![Vec::new(); MAX_PLY + 1],
+            played_moves: vec![None; MAX_PLY + 1],
+            tables,
+            stats: SearchStats::default(),
+            node_limit: None,
+            deadline: None,
+            stop: None,
+            completed_depth: 0,
+            aborted: false,
+            root_color: Color::White,
+            game_history: Vec::new(),
+            search_path: vec![0; MAX_PLY + 1],
+            tb_root_moves: None,
+        }
+    }
+
+    /// Prepares this searcher to value the leaves of an MCTS search from `root` with
+    /// `leaf_value`. Draws are scored with contempt relative to the side to move at `root`, and
+    /// the stop flag of `limits` interrupts a leaf search.
+    fn start_leaf_searches(&mut self, root: &Chess, limits: &SearchLimits) {
+        self.root_color = root.turn();
+        self.game_history.clear();
+        self.history.age();
+        self.tables.transposition_table.new_search();
+        self.stats = SearchStats::default();
+        self.node_limit = None;
+        self.deadline = None;
+        self.stop = limits.stop.clone();
+    }
+
+    /// Scores `pos` for the side to move with a search of `depth` plies that ends in the
+    /// quiescence search, or returns `None` if the search was stopped. The nodes are added to
+    /// the stats of this searcher.
+    fn leaf_value(&mut self, pos: &Chess, depth: u8, config: &SearchConfig) -> Option<i32> {
+        // Only a stop request interrupts a search before its first iteration completes.
+        self.completed_depth = 0;
+        self.aborted = false;
+        let params = PvsSearchParams {
+            pos,
+            depth,
+            ply: 0,
+            alpha: -MATE_SCORE,
+            beta: MATE_SCORE,
+            config,
+            build_tree: false,
+            verbose: false,
+        };
+        let (score, _) = self.alpha_beta(params);
+        (!self.aborted).then_some(score)
+    }
+
+    /// The score of a drawn position for the side to move. With a positive contempt factor
+    /// the root side treats draws as slightly losing, and its opponent as slightly winning.
+    fn draw_score(&self, pos: &Chess, config: &SearchConfig) -> i32 {
+        if pos.turn() == self.root_color {
+            -config.contempt_factor
+        } else {
+            config.contempt_factor
+        }
+    }
+
+    /// Whether the position at `ply` with the given hash already occurred, either earlier in
+    /// the search path or in the game. Only positions since the last capture or pawn move can
+    /// repeat, and the same side has to be to move.
+    fn is_repetition(&self, hash: u64, ply: usize, halfmoves: u32) -> bool {
+        let mut distance = 2;
+        while distance <= halfmoves as usize {
+            let earlier = if distance <= ply {
+                self.search_path[ply - distance]
+            } else {
+                match self.game_history.len().checked_sub(distance - ply) {
+                    Some(index) => self.game_history[index],
+                    None => return false,
+                }
+            };
+            if earlier == hash {
+                return true;
+            }
+            distance += 2;
+        }
+        false
+    }
+
+    /// Counts a node and reports whether the search has to stop.
+    ///
+    /// An explicit stop request is honoured immediately. Otherwise the first iteration is
+    /// always allowed to finish so that a move is available.
+    fn check_limits(&mut self) -> bool {
+        self.stats.nodes += 1;
+        if self.aborted {
+            return true;
+        }
+        if self.stop.as_ref().is_some_and(|stop| stop.load(Ordering::Relaxed)) {
+            self.aborted = true;
+            return true;
         }
+        if self.completed_depth == 0 {
+            return false;
+        }
+        let out_of_nodes = self.node_limit.is_some_and(|limit| self.stats.nodes >= limit);
+        let out_of_time = self.stats.nodes.is_multiple_of(1024)
+            && self.deadline.is_some_and(|d| Instant::now() >= d);
+        self.aborted = out_of_nodes || out_of_time;
+        self.aborted
     }
 
     fn pvs_root_search(
@@ -271,25 +912,36 @@ impl PvsSearcher {
         let mut legal_moves = args.pos.legal_moves();
         let mut root_node = MoveTreeNode {
             move_san: "root".to_string(),
-            score: 0,
+            score: Score::default(),
             children: Vec::new(),
         };
 
         if legal_moves.is_empty() {
             return (None, self.evaluate_with_cache(args.pos, args.config), root_node);
         }
+        if let Some(tb_root_moves) = &self.tb_root_moves {
+            legal_moves.retain(|m| tb_root_moves.contains(m));
+            self.stats.tb_hits += 1;
+        }
 
-        self.order_moves(&mut legal_moves, args.pos, 0, args.config, None);
+        let hash = args.pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal);
+        self.search_path[0] = hash.0;
+        let tt_move = self.probe_tt(&hash).and_then(|entry| entry.best_move);
+        self.order_moves(&mut legal_moves, args.pos, 0, args.config, tt_move);
 
+        let original_alpha = args.alpha;
         let mut best_move = None;
+        self.pv_table[0].clear();
 
         for m in legal_moves {
             let mut new_pos = args.pos.clone();
             new_pos.play_unchecked(m);
+            let extension = self.extension(args.pos, 0, args.config, m, &new_pos, None);
+            self.played_moves[0] = Some(m);
 
             let params = PvsSearchParams {
                 pos: &new_pos,
-                depth: args.depth - 1,
+                depth: args.depth - 1 + extension,
                 ply: 1,
                 alpha: -args.beta,
                 beta: -args.alpha,
@@ -299,26 +951,45 @@ impl PvsSearcher {
             };
             let (score, child_node) = self.alpha_beta(params);
             let score = -score;
+            if self.aborted {
+                break;
+            }
 
             if args.build_tree {
                 let san = SanPlus::from_move(args.pos.clone(), m);
                 let mut node = child_node.unwrap_or(MoveTreeNode {
                     move_san: "".to_string(),
-                    score: 0,
+                    score: Score::default(),
                     children: Vec::new(),
                 });
                 node.move_san = san.to_string();
-                node.score = score;
+                node.score = Score::from_search(score);
                 root_node.children.push(node);
             }
 
             if score > args.alpha {
                 args.alpha = score;
                 best_move = Some(m);
+                self.update_pv(0, m);
+            }
+            // A fail high above the aspiration window. The remaining moves would be searched
+            // with an inverted window, so the caller widens it and searches again instead.
+            if args.alpha >= args.beta {
+                break;
+            }
+        }
+
+        // Scores from an interrupted search are meaningless, so nothing is stored.
+        if !self.aborted {
+            if best_move.is_some() {
+                let bound = if args.alpha >= args.beta { Bound::Lower } else { Bound::Exact };
+                self.store_tt(hash, args.depth, args.alpha, 0, bound, best_move);
+            } else {
+                self.store_tt(hash, args.depth, original_alpha, 0, Bound::Upper, None);
             }
         }
 
-        root_node.score = args.alpha;
+        root_node.score = Score::from_search(args.alpha);
         (best_move, args.alpha, root_node)
     }
 
@@ -336,17 +1007,35 @@ impl PvsSearcher {
         &mut self,
         params: PvsSearchParams,
     ) -> (i32, Option<MoveTreeNode>) {
-        const NMP_DEPTH_REDUCTION: u8 = 3;
-        const NMP_MIN_DEPTH: u8 = 3;
+        let config = params.config;
+        let mut reduction = config.nmp_reduction;
+        if config.use_adaptive_nmp {
+            // Reduce more at high depth and when the position is far above beta.
+            reduction += params.depth / 4;
+            let eval = self.evaluate_with_cache(params.pos, config);
+            if eval > params.beta {
+                reduction += ((eval - params.beta) / config.nmp_eval_margin.max(1)).min(3) as u8;
+            }
+        }
 
         let non_pawn_material = params.pos.board().by_color(params.pos.turn()) & !params.pos.board().pawns();
         let is_likely_zugzwang = non_pawn_material.is_empty();
 
-        if params.depth >= NMP_MIN_DEPTH && !params.pos.is_check() && !is_likely_zugzwang {
+        // A stored upper bound below beta means the null move is very unlikely to fail high.
+        // The probe is not counted as a hit, since the search of this node probes again.
+        let hash = params.pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal);
+        let tt_says_fail_low = self.tables.transposition_table.probe(&hash).is_some_and(|entry| {
+            entry.bound != Bound::Lower
+                && entry.depth >= params.depth.saturating_sub(reduction)
+                && score_from_tt(entry.score, params.ply) < params.beta
+        });
+
+        if params.depth >= config.nmp_min_depth && !params.pos.is_check() && !is_likely_zugzwang && !tt_says_fail_low {
             if let Ok(null_move_pos) = params.pos.clone().swap_turn() {
+                self.played_moves[params.ply as usize] = None;
                 let null_move_params = PvsSearchParams {
                     pos: &null_move_pos,
-                    depth: params.depth.saturating_sub(NMP_DEPTH_REDUCTION),
+                    depth: params.depth.saturating_sub(reduction),
                     ply: params.ply + 1,
                     alpha: -params.beta,
                     beta: -params.beta + 1,
@@ -356,9 +1045,13 @@ impl PvsSearcher {
                 };
                 let (score, _) = self.pvs_search(null_move_params);
                 let score = -score;
+                if self.aborted {
+                    return (0, None);
+                }
                 if score >= params.beta {
+                    self.store_tt(hash, params.depth, params.beta, params.ply, Bound::Lower, None);
                     let node = if params.build_tree {
-                        Some(MoveTreeNode { move_san: "null".to_string(), score: params.beta, children: vec![] })
+                        Some(MoveTreeNode { move_san: "null".to_string(), score: Score::from_search(params.beta), children: vec![] })
                     } else {
                         None
                     };
@@ -373,42 +1066,152 @@ impl PvsSearcher {
         &mut self,
         mut params: PvsSearchParams,
     ) -> (i32, Option<MoveTreeNode>) {
-        const LMR_MIN_DEPTH: u8 = 3;
-        const LMR_MIN_MOVE_INDEX: usize = 2;
         const FUTILITY_MARGIN_PER_DEPTH: [i32; 4] = [0, 100, 250, 500];
+        const SINGULAR_MIN_DEPTH: u8 = 6;
+        const RFP_MAX_DEPTH: u8 = 6;
+        const RAZORING_MAX_DEPTH: u8 = 3;
+        const LMP_MAX_DEPTH: u8 = 4;
+        const PROBCUT_MIN_DEPTH: u8 = 5;
+        const PROBCUT_REDUCTION: u8 = 4;
 
         let mut current_node = if params.build_tree {
             Some(MoveTreeNode {
                 move_san: "".to_string(), // This will be set by the parent
-                score: params.alpha,
+                score: Score::from_search(params.alpha),
                 children: Vec::new(),
             })
         } else {
             None
         };
 
-        if params.pos.is_game_over() && params.pos.outcome().winner().is_none() {
+        if self.check_limits() {
             return (0, current_node);
         }
+        self.stats.seldepth = self.stats.seldepth.max(params.ply);
+        self.pv_table[params.ply as usize].clear();
+
+        if params.ply as usize >= MAX_PLY - 1 {
+            return (self.evaluate_with_cache(params.pos, params.config), current_node);
+        }
+
+        let hash = params.pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal);
+        self.search_path[params.ply as usize] = hash.0;
+        if self.is_repetition(hash.0, params.ply as usize, params.pos.halfmoves()) {
+            return (self.draw_score(params.pos, params.config), current_node);
+        }
+
+        if params.pos.is_game_over() && params.pos.outcome().winner().is_none() {
+            return (self.draw_score(params.pos, params.config), current_node);
+        }
 
         let mut legal_moves = params.pos.legal_moves();
         if legal_moves.is_empty() {
             if params.pos.is_checkmate() { return (-MATE_SCORE + params.ply as i32, current_node); }
-            return (0, current_node); // Stalemate
+            return (self.draw_score(params.pos, params.config), current_node); // Stalemate
+        }
+        // Checkmate on the hundredth half-move takes precedence over the fifty-move rule.
+        if params.pos.halfmoves() >= 100 {
+            return (self.draw_score(params.pos, params.config), current_node);
+        }
+
+        let original_alpha = params.alpha;
+        let is_pv_node = params.beta - params.alpha > 1;
+        let mut tt_move = None;
+        let tt_entry = self.probe_tt(&hash).filter(|_| params.depth > 0);
+        if let Some(entry) = tt_entry {
+            tt_move = entry.best_move;
+            if !is_pv_node && entry.depth >= params.depth {
+                let tt_score = score_from_tt(entry.score, params.ply);
+                let cutoff = match entry.bound {
+                    Bound::Exact => true,
+                    Bound::Lower => tt_score >= params.beta,
+                    Bound::Upper => tt_score <= params.alpha,
+                };
+                if cutoff {
+                    let score = tt_score.clamp(params.alpha, params.beta);
+                    if let Some(node) = &mut current_node {
+                        node.score = Score::from_search(score);
+                    }
+                    return (score, current_node);
+                }
+            }
+        }
+
+        // Right after a capture or pawn move, the tables know the result under the fifty-move rule.
+        if params.pos.halfmoves() == 0 {
+            let wdl = self.tables.tablebases.as_ref().and_then(|tb| tb.probe_wdl_after_zeroing(params.pos));
+            if let Some(wdl) = wdl {
+                self.stats.tb_hits += 1;
+                let score = match wdl {
+                    Wdl::Win => TB_WIN_SCORE - params.ply as i32,
+                    Wdl::Loss => -TB_WIN_SCORE + params.ply as i32,
+                    Wdl::CursedWin | Wdl::Draw | Wdl::BlessedLoss => self.draw_score(params.pos, params.config),
+                };
+                if let Some(node) = &mut current_node {
+                    node.score = Score::from_search(score);
+                }
+                return (score, current_node);
+            }
         }
 
         if params.depth == 0 {
             let score = if params.config.use_quiescence_search {
-                self.quiescence_search(params.pos, params.alpha, params.beta, params.config, params.verbose)
+                self.quiescence_search(params.pos, params.alpha, params.beta, params.ply, params.config, params.verbose)
             } else {
                 self.evaluate_with_cache(params.pos, params.config)
             };
             if let Some(node) = &mut current_node {
-                node.score = score;
+                node.score = Score::from_search(score);
             }
             return (score, current_node);
         }
 
+        let in_check = params.pos.is_check();
+        let can_prune = !is_pv_node && !in_check && params.ply > 0 && params.beta.abs() < MATE_THRESHOLD;
+
+        // Reverse futility pruning: the static evaluation beats beta by so much that the
+        // opponent is not expected to recover within the remaining depth.
+        if can_prune && params.config.use_reverse_futility_pruning && params.depth <= RFP_MAX_DEPTH {
+            let eval = self.evaluate_with_cache(params.pos, params.config);
+            if eval - params.config.reverse_futility_margin.max(0) * params.depth as i32 >= params.beta {
+                if let Some(node) = &mut current_node {
+                    node.score = Score::from_search(params.beta);
+                }
+                return (params.beta, current_node);
+            }
+        }
+
+        // Razoring: far below alpha near the leaves, only tactics can help, so let the
+        // quiescence search confirm the fail-low.
+        if can_prune && params.config.use_razoring && params.depth <= RAZORING_MAX_DEPTH {
+            let eval = self.evaluate_with_cache(params.pos, params.config);
+            if eval + params.config.razoring_margin.max(0) * (params.depth as i32) < params.alpha {
+                let score = self.quiescence_search(params.pos, params.alpha - 1, params.alpha, params.ply, params.config, params.verbose);
+                if score < params.alpha {
+                    if let Some(node) = &mut current_node {
+                        node.score = Score::from_search(params.alpha);
+                    }
+                    return (params.alpha, current_node);
+                }
+            }
+        }
+
+        // ProbCut: a good capture that beats beta by a margin in a much shallower search will
+        // almost certainly beat beta in the full one.
+        if can_prune && params.config.use_probcut && params.depth >= PROBCUT_MIN_DEPTH {
+            let probcut_beta = params.beta + params.config.probcut_margin.max(0);
+            if self.probcut(&params, &legal_moves, probcut_beta, PROBCUT_REDUCTION) {
+                self.store_tt(hash, params.depth - PROBCUT_REDUCTION + 1, params.beta, params.ply, Bound::Lower, None);
+                if let Some(node) = &mut current_node {
+                    node.score = Score::from_search(params.beta);
+                }
+                return (params.beta, current_node);
+            }
+            if self.aborted {
+                return (0, current_node);
+            }
+        }
+
         if params.config.use_futility_pruning && (params.depth as usize) < FUTILITY_MARGIN_PER_DEPTH.len() {
             let eval = self.evaluate_with_cache(params.pos, params.config);
             let margin = FUTILITY_MARGIN_PER_DEPTH[params.depth as usize];
@@ -420,24 +1223,60 @@ impl PvsSearcher {
                         params.ply, eval, margin, params.alpha, fen
                     );
                 }
-                let score = self.quiescence_search(params.pos, params.alpha, params.beta, params.config, params.verbose);
+                let score = self.quiescence_search(params.pos, params.alpha, params.beta, params.ply, params.config, params.verbose);
                 if let Some(node) = &mut current_node {
-                    node.score = score;
+                    node.score = Score::from_search(score);
                 }
                 return (score, current_node)
             }
         }
 
-        self.order_moves(&mut legal_moves, params.pos, params.ply, params.config, None);
+        self.order_moves(&mut legal_moves, params.pos, params.ply, params.config, tt_move);
+
+        // The hash move is singular if every alternative fails low against a margin below its
+        // stored score in a reduced search; it is then worth searching one ply deeper.
+        let singular_move = tt_entry.and_then(|entry| {
+            let tt_move = entry.best_move?;
+            let tt_score = score_from_tt(entry.score, params.ply);
+            let candidate = params.config.use_singular_extensions
+                && params.ply > 0
+                && params.depth >= SINGULAR_MIN_DEPTH
+                && entry.bound != Bound::Upper
+                && entry.depth + 3 >= params.depth
+                && tt_score.abs() < MATE_THRESHOLD
+                && legal_moves.contains(&tt_move);
+            (candidate && self.is_singular(&params, &legal_moves, tt_move, tt_score)).then_some(tt_move)
+        });
+        if self.aborted {
+            return (0, current_node);
+        }
 
+        let mut best_move = None;
+        let mut quiets_searched = Vec::new();
         for (i, m) in legal_moves.into_iter().enumerate() {
             let mut new_pos = params.pos.clone();
             new_pos.play_unchecked(m);
+            let extension = self.extension(params.pos, params.ply, params.config, m, &new_pos, singular_move);
+
+            // Late move pruning: near the leaves, quiet moves this far down the ordering are
+            // unlikely to matter and are skipped.
+            if can_prune
+                && params.config.use_late_move_pruning
+                && params.depth <= LMP_MAX_DEPTH
+                && extension == 0
+                && !m.is_capture()
+                && !m.is_promotion()
+                && !new_pos.is_check()
+                && i >= params.config.late_move_pruning_base.max(1) as usize + (params.depth as usize).pow(2)
+            {
+                continue;
+            }
+            self.played_moves[params.ply as usize] = Some(m);
 
             let (score, child_node) = if i == 0 {
                  let next_params = PvsSearchParams {
                     pos: &new_pos,
-                    depth: params.depth - 1,
+                    depth: params.depth - 1 + extension,
                     ply: params.ply + 1,
                     alpha: -params.beta,
                     beta: -params.alpha,
@@ -450,18 +1289,20 @@ impl PvsSearcher {
             } else {
                 let mut reduction = 0;
                 if params.config.use_lmr
-                    && params.depth >= LMR_MIN_DEPTH
-                    && i >= LMR_MIN_MOVE_INDEX
-                    && !params.pos.is_check()
+                    && extension == 0
+                    && params.depth >= params.config.lmr_min_depth
+                    && i >= params.config.lmr_min_move_index as usize
+                    && !in_check
                     && !m.is_capture()
                 {
-                    reduction = (1.0 + (params.depth as f32).ln() * (i as f32).ln() / 2.0).floor() as u8;
+                    let divisor = params.config.lmr_divisor.max(1) as f32 / 100.0;
+                    reduction = (1.0 + (params.depth as f32).ln() * (i as f32).ln() / divisor).floor() as u8;
                     reduction = reduction.min(params.depth - 1);
                 }
 
                 let zw_params = PvsSearchParams {
                     pos: &new_pos,
-                    depth: params.depth - 1 - reduction,
+                    depth: params.depth - 1 + extension - reduction,
                     ply: params.ply + 1,
                     alpha: -params.alpha - 1,
                     beta: -params.alpha,
@@ -475,7 +1316,7 @@ impl PvsSearcher {
                 if zw_score > params.alpha && zw_score < params.beta {
                     let next_params = PvsSearchParams {
                         pos: &new_pos,
-                        depth: params.depth - 1,
+                        depth: params.depth - 1 + extension,
                         ply: params.ply + 1,
                         alpha: -params.beta,
                         beta: -params.alpha,
@@ -489,62 +1330,245 @@ impl PvsSearcher {
                     (zw_score, child_node)
                 }
             };
+            if self.aborted {
+                return (0, current_node);
+            }
 
             if let Some(node) = &mut current_node {
                 let san = SanPlus::from_move(params.pos.clone(), m);
                 let mut new_node = child_node.unwrap_or(MoveTreeNode {
                     move_san: "".to_string(),
-                    score: 0,
+                    score: Score::default(),
                     children: Vec::new(),
                 });
                 new_node.move_san = san.to_string();
-                new_node.score = score;
+                new_node.score = Score::from_search(score);
                 node.children.push(new_node);
             }
 
             if score >= params.beta {
+                self.stats.beta_cutoffs += 1;
+                if i == 0 {
+                    self.stats.first_move_cutoffs += 1;
+                }
                 if params.config.use_killer_moves && !m.is_capture() {
                     self.killer_moves[params.ply as usize][1] = self.killer_moves[params.ply as usize][0];
                     self.killer_moves[params.ply as usize][0] = Some(m);
                 }
+                if is_quiet(&m) {
+                    self.update_quiet_history(&params, m, &quiets_searched);
+                }
+                self.store_tt(hash, params.depth, params.beta, params.ply, Bound::Lower, Some(m));
                 if let Some(node) = &mut current_node {
-                    node.score = params.beta;
+                    node.score = Score::from_search(params.beta);
                 }
                 return (params.beta, current_node);
             }
             if score > params.alpha {
                 params.alpha = score;
-                if params.config.use_history_heuristic {
-                    if let Some(from_sq) = m.from() {
-                        let piece_index = self.get_piece_index(params.pos.board().piece_at(from_sq).unwrap());
-                        self.history_table[piece_index][m.to() as usize] += (params.depth as i32).pow(2);
-                    }
-                }
+                best_move = Some(m);
+                self.update_pv(params.ply as usize, m);
+            }
+            if is_quiet(&m) {
+                quiets_searched.push(m);
             }
         }
+        let bound = if params.alpha > original_alpha { Bound::Exact } else { Bound::Upper };
+        self.store_tt(hash, params.depth, params.alpha, params.ply, bound, best_move);
+
         if let Some(node) = &mut current_node {
-            node.score = params.alpha;
+            node.score = Score::from_search(params.alpha);
         }
         (params.alpha, current_node)
     }
 
-    fn get_piece_index(&self, piece: Piece) -> usize {
-        // Shakmaty's Role enum appears to be 1-indexed when cast to usize,
-        // with Pawn = 1, King = 6. We subtract 1 to get a 0-based index.
-        (piece.color as usize * 6) + (piece.role as usize - 1)
+    /// How many plies to extend the search of `m`, which leads to `new_pos`. Extensions do not
+    /// stack, and stop halfway to the ply limit so that a chain of checks cannot run away.
+    fn extension(&self, pos: &Chess, ply: u8, config: &SearchConfig, m: Move, new_pos: &Chess, singular_move: Option<Move>) -> u8 {
+        if ply as usize >= MAX_PLY / 2 {
+            return 0;
+        }
+        if config.use_check_extensions && new_pos.is_check() {
+            return 1;
+        }
+        if config.use_singular_extensions && singular_move == Some(m) {
+            return 1;
+        }
+        if config.use_recapture_extensions && m.is_capture() && ply > 0 {
+            let previous = self.played_moves[ply as usize - 1];
+            if previous.is_some_and(|p| p.is_capture() && p.to() == m.to()) {
+                return 1;
+            }
+        }
+        // No enemy pawn can stand in front of a pawn on the seventh rank, so it is always passed.
+        if config.use_passed_pawn_extensions
+            && m.role() == Role::Pawn
+            && m.to().rank() == pos.turn().relative_rank(shakmaty::Rank::Seventh)
+        {
+            return 1;
+        }
+        0
+    }
+
+    /// Whether a capture that does not lose material reaches `probcut_beta` in a zero-window
+    /// search reduced by `reduction` plies. Each capture is first verified by the quiescence
+    /// search, which is much cheaper.
+    fn probcut(&mut self, params: &PvsSearchParams, moves: &[Move], probcut_beta: i32, reduction: u8) -> bool {
+        for &m in moves.iter().filter(|m| m.is_capture()) {
+            if !m.is_en_passant() && evaluation::see::see(params.pos.board(), m.from().unwrap(), m.to()) < 0 {
+                continue;
+            }
+            let mut new_pos = params.pos.clone();
+            new_pos.play_unchecked(m);
+            self.played_moves[params.ply as usize] = Some(m);
+
+            let qs_score = -self.quiescence_search(&new_pos, -probcut_beta, -probcut_beta + 1, params.ply + 1, params.config, false);
+            if self.aborted {
+                return false;
+            }
+            if qs_score < probcut_beta {
+                continue;
+            }
+
+            let child_params = PvsSearchParams {
+                pos: &new_pos,
+                depth: params.depth - reduction,
+                ply: params.ply + 1,
+                alpha: -probcut_beta,
+                beta: -probcut_beta + 1,
+                config: params.config,
+                build_tree: false,
+                verbose: false,
+            };
+            let score = -self.pvs_search(child_params).0;
+            if self.aborted {
+                return false;
+            }
+            if score >= probcut_beta {
+                return true;
+            }
+        }
+        false
     }
 
-    fn order_moves(&self, moves: &mut [Move], pos: &Chess, ply: u8, config: &SearchConfig, tt_move: Option<Move>) {
-        moves.sort_unstable_by(|a, b| {
-            let a_score = self.score_move(a, pos, ply, config, tt_move);
-            let b_score = self.score_move(b, pos, ply, config, tt_move);
-            b_score.cmp(&a_score)
+    /// Whether every move other than `tt_move` fails low against `tt_score` minus a margin in
+    /// a reduced-depth zero-window search.
+    fn is_singular(&mut self, params: &PvsSearchParams, moves: &[Move], tt_move: Move, tt_score: i32) -> bool {
+        let singular_beta = tt_score - 2 * params.depth as i32;
+        let depth = (params.depth - 1) / 2;
+        for &m in moves.iter().filter(|&&m| m != tt_move) {
+            let mut new_pos = params.pos.clone();
+            new_pos.play_unchecked(m);
+            self.played_moves[params.ply as usize] = Some(m);
+            let child_params = PvsSearchParams {
+                pos: &new_pos,
+                depth,
+                ply: params.ply + 1,
+                alpha: -singular_beta,
+                beta: -singular_beta + 1,
+                config: params.config,
+                build_tree: false,
+                verbose: false,
+            };
+            let score = -self.pvs_search(child_params).0;
+            if self.aborted || score >= singular_beta {
+                return false;
+            }
+        }
+        true
+    }
+
+    /// Sets the PV at `ply` to `m` followed by the PV of the child node.
+    fn update_pv(&mut self, ply: usize, m: Move) {
+        let (current, rest) = self.pv_table.split_at_mut(ply + 1);
+        let line = &mut current[ply];
+        line.clear();
+        line.push(m);
+        line.extend_from_slice(&rest[0]);
+    }
+
+    fn probe_tt(&mut self, hash: &Zobrist64) -> Option<TtEntry> {
+        let entry = self.tables.transposition_table.probe(hash);
+        if entry.is_some() {
+            self.stats.tt_hits += 1;
+        }
+        entry
+    }
+
+    fn store_tt(&self, hash: Zobrist64, depth: u8, score: i32, ply: u8, bound: Bound, best_move: Option<Move>) {
+        self.tables.transposition_table.store(hash, TtEntry {
+            depth,
+            score: score_to_tt(score, ply),
+            bound,
+            best_move,
         });
     }
 
-    fn score_move(&self, m: &Move, pos: &Chess, ply: u8, config: &SearchConfig, _: Option<Move>) -> i32 {
+    /// The move that led to the node at `ply`, if it is known and was not a null move.
+    fn previous_move(&self, ply: u8) -> Option<Move> {
+        if ply == 0 {
+            None
+        } else {
+            self.played_moves[ply as usize - 1]
+        }
+    }
+
+    /// Rewards the quiet move `m` for causing a cutoff and penalises the quiet moves searched
+    /// before it, which failed to.
+    fn update_quiet_history(&mut self, params: &PvsSearchParams, m: Move, quiets_searched: &[Move]) {
+        let config = params.config;
+        let color = params.pos.turn();
+        let bonus = MoveHistory::bonus(params.depth);
+        let previous = self.previous_move(params.ply);
+        if config.use_history_heuristic {
+            self.history.update_butterfly(color, &m, bonus);
+            for failed in quiets_searched {
+                self.history.update_butterfly(color, failed, -bonus);
+            }
+        }
+        if let Some(previous) = previous {
+            if config.use_continuation_history {
+                self.history.update_continuation(color, &m, &previous, bonus);
+                for failed in quiets_searched {
+                    self.history.update_continuation(color, failed, &previous, -bonus);
+                }
+            }
+            if config.use_counter_moves {
+                self.history.set_counter_move(color, &previous, m);
+            }
+        }
+    }
+
+    fn order_moves(&self, moves: &mut [Move], pos: &Chess, ply: u8, config: &SearchConfig, tt_move: Option<Move>) {
+        // Scoring a capture runs SEE, so compute each key once rather than on every comparison.
+        moves.sort_by_cached_key(|m| std::cmp::Reverse(self.score_move(m, pos, ply, config, tt_move)));
+    }
+
+    /// Move ordering, from first to last: the hash move, queen promotions and captures that
+    /// do not lose material (by MVV-LVA), killers, the counter move, losing captures (by SEE),
+    /// quiet moves by history, and finally under-promotions.
+    fn score_move(&self, m: &Move, pos: &Chess, ply: u8, config: &SearchConfig, tt_move: Option<Move>) -> i32 {
+        if Some(*m) == tt_move {
+            return 2_000_000; // The hash move is searched before everything else
+        }
+        if let Some(promotion) = m.promotion() {
+            if promotion == Role::Queen {
+                return 1_000_000 + mvv_lva(m) + evaluation::get_piece_value(Role::Queen);
+            }
+            return -1_000_000 + evaluation::get_piece_value(promotion);
+        }
         if m.is_capture() {
-            return 1_000_000; // High score for captures to search them first
+            // En passant leaves the target square empty, which SEE cannot see; it is a pawn
+            // trade at worst.
+            let see = if m.is_en_passant() {
+                0
+            } else {
+                evaluation::see::see(pos.board(), m.from().unwrap(), m.to())
+            };
+            if see >= 0 {
+                return 1_000_000 + mvv_lva(m);
+            }
+            return 700_000 + see;
         }
         if config.use_killer_moves {
             if Some(*m) == self.killer_moves[ply as usize][0] {
@@ -554,57 +1578,93 @@ impl PvsSearcher {
                 return 800_000;
             }
         }
+        let color = pos.turn();
+        let previous = self.previous_move(ply);
+        if config.use_counter_moves
+            && previous.is_some_and(|previous| self.history.counter_move(color, &previous) == Some(*m))
+        {
+            return 750_000;
+        }
+        let mut score = 0;
         if config.use_history_heuristic {
-            if let Some(from_sq) = m.from() {
-                let piece_index = self.get_piece_index(pos.board().piece_at(from_sq).unwrap());
-                return self.history_table[piece_index][m.to() as usize];
-            }
+            score += self.history.butterfly(color, m);
         }
-        0
+        if let Some(previous) = previous.filter(|_| config.use_continuation_history) {
+            score += self.history.continuation(color, m, &previous);
+        }
+        score
     }
 
     fn evaluate_with_cache(&self, pos: &Chess, config: &SearchConfig) -> i32 {
         let hash = pos.zobrist_hash::<evaluation_cache::Zobrist64>(EnPassantMode::Legal);
-        if let Some(score) = self.evaluation_cache.lock().unwrap().probe(&hash) {
+        if let Some(score) = self.tables.evaluation_cache.probe(&hash) {
             return score;
         }
 
         let score = evaluation::evaluate(pos, config);
-        self.evaluation_cache.lock().unwrap().store(evaluation_cache::CacheEntry { hash, score });
+        self.tables.evaluation_cache.store(hash, score);
         score
     }
 
-    fn quiescence_search(&self, pos: &Chess, mut alpha: i32, beta: i32, config: &SearchConfig, verbose: bool) -> i32 {
-        if config.use_delta_pruning {
-            return self.delta_search(pos, alpha, beta, config, verbose);
+    fn quiescence_search(&mut self, pos: &Chess, alpha: i32, beta: i32, ply: u8, config: &SearchConfig, verbose: bool) -> i32 {
+        self.qsearch(pos, alpha, beta, ply, 0, config, verbose)
+    }
+
+
+    /// Searches tactical moves until the position is quiet. `qs_ply` counts the plies since
+    /// the main search handed over, so quiet checks are only tried at the first one.
+    #[allow(clippy::too_many_arguments)]
+    fn qsearch(&mut self, pos: &Chess, mut alpha: i32, beta: i32, ply: u8, qs_ply: u8, config: &SearchConfig, verbose: bool) -> i32 {
+        if self.check_limits() {
+            return 0;
         }
+        self.stats.qnodes += 1;
+        self.stats.seldepth = self.stats.seldepth.max(ply);
 
-        let standing_pat = self.evaluate_with_cache(pos, config);
-        if standing_pat >= beta {
-            return beta;
+        let in_check = pos.is_check();
+        let mut legal_moves = pos.legal_moves();
+        if legal_moves.is_empty() {
+            return if in_check { -MATE_SCORE + ply as i32 } else { self.draw_score(pos, config) };
         }
-        if alpha < standing_pat {
-            alpha = standing_pat;
+        if ply as usize >= MAX_PLY - 1 {
+            return self.evaluate_with_cache(pos, config);
         }
 
-        let captures = pos.legal_moves().into_iter().filter(|m| m.is_capture());
-
-        for m in captures {
-            if evaluation::see::see(pos.board(), m.from().unwrap(), m.to()) < 0 {
-                continue;
+        let moves = if in_check {
+            // Standing pat is not an option in check, so every evasion has to be searched.
+            self.order_moves(&mut legal_moves, pos, ply, config, None);
+            legal_moves.into_iter().collect()
+        } else {
+            let standing_pat = self.evaluate_with_cache(pos, config);
+            if standing_pat >= beta {
+                return beta;
+            }
+            if alpha < standing_pat {
+                alpha = standing_pat;
             }
-            let mut new_pos = pos.clone();
-            new_pos.play_unchecked(m);
 
-            if new_pos.legal_moves().is_empty() {
-                if new_pos.is_checkmate() {
-                    return MATE_SCORE - 100;
+            if config.use_delta_pruning {
+                // Even winning a queen cannot raise alpha, so no capture here will.
+                let queen_value = evaluation::get_piece_value(Role::Queen);
+                if standing_pat + queen_value < alpha {
+                    if verbose {
+                        println!(
+                            "[delta] Delta Pruning: standing_pat ({standing_pat}) + queen_value ({queen_value}) < alpha ({alpha})."
+                        );
+                    }
+                    return alpha;
                 }
-                return 0;
             }
 
+            let include_checks = config.use_qs_checks && qs_ply == 0;
+            quiescence_moves(pos, include_checks)
+        };
 
-            let score = -self.quiescence_search(&new_pos, -beta, -alpha, config, verbose);
+        for m in moves {
+            let mut new_pos = pos.clone();
+            new_pos.play_unchecked(m);
+            self.played_moves[ply as usize] = Some(m);
+            let score = -self.qsearch(&new_pos, -beta, -alpha, ply.saturating_add(1), qs_ply.saturating_add(1), config, verbose);
 
             if score >= beta {
                 if verbose {
@@ -619,47 +1679,52 @@ impl PvsSearcher {
         }
         alpha
     }
+}
 
-    fn delta_search(&self, pos: &Chess, mut alpha: i32, beta: i32, config: &SearchConfig, verbose: bool) -> i32 {
-        let standing_pat = self.evaluate_with_cache(pos, config);
-        if standing_pat >= beta {
-            return beta;
-        }
-        if alpha < standing_pat {
-            alpha = standing_pat;
-        }
-
-        if config.use_delta_pruning {
-            let queen_value = evaluation::get_piece_value(shakmaty::Role::Queen);
-            if standing_pat - queen_value >= beta {
-                if verbose {
-                    println!(
-                        "[delta] Delta Pruning: standing_pat ({standing_pat}) - queen_value ({queen_value}) >= beta ({beta})."
-                    );
-                }
-                return beta;
-            }
-        }
+/// Quiet moves are the ones ordered by the history heuristics.
+fn is_quiet(m: &Move) -> bool {
+    !m.is_capture() && !m.is_promotion()
+}
 
-        let captures = pos.legal_moves().into_iter().filter(|m| m.is_capture());
+/// Most Valuable Victim - Least Valuable Attacker: prefers taking the biggest piece with the
+/// smallest one. Returns 0 for quiet moves.
+fn mvv_lva(m: &Move) -> i32 {
+    match m.capture() {
+        Some(victim) => evaluation::get_piece_value(victim) * 10 - evaluation::get_piece_value(m.role()),
+        None => 0,
+    }
+}
 
-        for m in captures {
-            if evaluation::see::see(pos.board(), m.from().unwrap(), m.to()) < 0 {
-                continue;
+/// The moves searched by the quiescence search when not in check: queen promotions and
+/// captures that do not lose material, ordered by MVV-LVA, followed by quiet checks that do
+/// not hang the checking piece if `include_checks` is set. Castling is left out of the checks,
+/// as its destination square is not where the checking rook lands.
+fn quiescence_moves(pos: &Chess, include_checks: bool) -> Vec<Move> {
+    let mut tactical = Vec::new();
+    let mut checks = Vec::new();
+    for m in pos.legal_moves() {
+        if m.promotion() == Some(Role::Queen) {
+            tactical.push(m);
+        } else if m.is_capture() {
+            // SEE cannot see the pawn taken en passant, but that capture never loses material.
+            if m.is_en_passant() || evaluation::see::see(pos.board(), m.from().unwrap(), m.to()) >= 0 {
+                tactical.push(m);
             }
-
+        } else if include_checks && m.promotion().is_none() && !m.is_castle() {
             let mut new_pos = pos.clone();
             new_pos.play_unchecked(m);
-            let score = -self.delta_search(&new_pos, -beta, -alpha, config, verbose);
-
-            if score >= beta {
-                return beta;
-            }
-            if score > alpha {
-                alpha = score;
+            if new_pos.is_check() && evaluation::see::see(pos.board(), m.from().unwrap(), m.to()) >= 0 {
+                checks.push(m);
             }
         }
-
-        alpha
     }
-}
\ No newline at end of file
+    tactical.sort_by_cached_key(|m| {
+        let promotion_bonus = if m.is_promotion() { evaluation::get_piece_value(Role::Queen) } else { 0 };
+        std::cmp::Reverse(mvv_lva(m) + promotion_bonus)
+    });
+    tactical.extend(checks);
+    tactical
+}
+
+#[cfg(test)]
+mod tests;
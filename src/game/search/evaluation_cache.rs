@@ -1,63 +1,53 @@
 // src/game/search/evaluation_cache.rs
 
 pub use shakmaty::zobrist::Zobrist64;
-use serde::{Deserialize, Serialize};
-use std::collections::{HashMap, VecDeque};
-use crate::constants::EVALUATION_CACHE_SIZE;
+use super::hash_table::{HashTable, Replacement, TableEntry};
+use crate::constants::EVALUATION_CACHE_MB;
 
-mod zobrist_serde {
-    use serde::{self, Deserializer, Serializer, Deserialize};
-    use shakmaty::zobrist::Zobrist64;
-
-    pub fn serialize<S>(zobrist: &Zobrist64, serializer: S) -> Result<S::Ok, S::Error>
-    where
-        S: Serializer,
-    {
-        serializer.serialize_u64(zobrist.0)
+impl TableEntry for i32 {
+    fn pack(self) -> u64 {
+        self as u32 as u64
     }
 
-    pub fn deserialize<'de, D>(deserializer: D) -> Result<Zobrist64, D::Error>
-    where
-        D: Deserializer<'de>,
-    {
-        let value = u64::deserialize(deserializer)?;
-        Ok(Zobrist64(value))
+    fn unpack(data: u64) -> Self {
+        data as u32 as i32
     }
 }
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
-pub struct CacheEntry {
-    #[serde(with = "zobrist_serde")]
-    pub hash: Zobrist64,
-    pub score: i32,
+/// Static evaluations of positions. Scores depend on the evaluation weights, so a cache must
+/// only be shared by searches with the same configuration.
+pub struct EvaluationCache {
+    table: HashTable<i32>,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize)]
-pub struct EvaluationCache {
-    table: HashMap<u64, i32>,
-    order: VecDeque<u64>,
+impl Default for EvaluationCache {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl EvaluationCache {
-    #[cfg_attr(test, allow(dead_code))]
     pub fn new() -> Self {
+        Self::with_size_mb(EVALUATION_CACHE_MB)
+    }
+
+    pub fn with_size_mb(size_mb: usize) -> Self {
         Self {
-            table: HashMap::with_capacity(EVALUATION_CACHE_SIZE),
-            order: VecDeque::with_capacity(EVALUATION_CACHE_SIZE),
+            table: HashTable::with_size_mb(size_mb, Replacement::Always),
         }
     }
 
+    /// The number of entries the table can hold.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn capacity(&self) -> usize {
+        self.table.capacity()
+    }
+
     pub fn probe(&self, hash: &Zobrist64) -> Option<i32> {
-        self.table.get(&hash.0).copied()
+        self.table.probe(hash.0)
     }
 
-    pub fn store(&mut self, entry: CacheEntry) {
-        if self.table.len() >= EVALUATION_CACHE_SIZE {
-            if let Some(oldest_hash) = self.order.pop_front() {
-                self.table.remove(&oldest_hash);
-            }
-        }
-        self.table.insert(entry.hash.0, entry.score);
-        self.order.push_back(entry.hash.0);
+    pub fn store(&self, hash: Zobrist64, score: i32) {
+        self.table.store(hash.0, score);
     }
-}
\ No newline at end of file
+}
@@ -0,0 +1,188 @@
+// src/game/search/hash_table.rs
+
+use std::marker::PhantomData;
+use std::sync::atomic::{AtomicU64, AtomicU8, Ordering};
+
+/// Entries per bucket. Four 16-byte entries fill one 64-byte cache line.
+const BUCKET_SIZE: usize = 4;
+const ENTRY_BYTES: usize = 16;
+
+/// The top byte of the data word holds the generation the entry was written in, so that the
+/// key check covers it along with the entry. Entries pack into the bits below it.
+const GENERATION_SHIFT: u32 = 56;
+const ENTRY_MASK: u64 = (1 << GENERATION_SHIFT) - 1;
+
+/// Priority an entry loses for every search that started since it was written.
+const AGE_PENALTY: i32 = 8;
+
+/// A value that can be stored in a `HashTable` by packing it into the low 56 bits of a word.
+pub trait TableEntry: Copy {
+    fn pack(self) -> u64;
+    fn unpack(data: u64) -> Self;
+
+    /// How valuable the entry is to keep, e.g. the depth it was searched to.
+    fn priority(&self) -> u8 {
+        0
+    }
+}
+
+/// Which entry a store overwrites.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Replacement {
+    /// New entries always go in. A position without an entry takes an empty slot of its
+    /// bucket, or else a slot picked by its hash.
+    Always,
+    /// An entry from the current search is only overwritten by one of at least the same
+    /// priority. A position without an entry replaces the entry of its bucket with the lowest
+    /// priority, where entries from earlier searches lose `AGE_PENALTY` per search.
+    PriorityPreferred,
+}
+
+#[derive(Default)]
+struct Slot {
+    /// The position hash XOR the data.
+    key: AtomicU64,
+    /// The packed entry, with the generation in the top byte. A generation of 0 marks an
+    /// empty slot.
+    data: AtomicU64,
+}
+
+/// A fixed-size, bucketed hash table that threads can share without locking.
+///
+/// Each slot is two atomic words: the data, and the position hash XOR the data. If two threads
+/// write the same slot at once, the words may come from different writes. Such a torn slot no
+/// longer matches either position, so it reads as a miss instead of returning the wrong entry.
+/// The table size is a power of two, so the bucket index is the low bits of the hash.
+pub struct HashTable<T> {
+    slots: Box<[Slot]>,
+    bucket_mask: usize,
+    replacement: Replacement,
+    /// Starts at 1 and skips 0 when it wraps, so that a generation of 0 marks an empty slot.
+    generation: AtomicU8,
+    _entry: PhantomData<fn() -> T>,
+}
+
+impl<T: TableEntry> HashTable<T> {
+    /// Creates a table of at most `size_mb` megabytes, and at least one bucket.
+    pub fn with_size_mb(size_mb: usize, replacement: Replacement) -> Self {
+        let buckets = ((size_mb << 20) / (BUCKET_SIZE * ENTRY_BYTES)).max(1);
+        let buckets = if buckets.is_power_of_two() { buckets } else { buckets.next_power_of_two() / 2 };
+        Self {
+            slots: (0..buckets * BUCKET_SIZE).map(|_| Slot::default()).collect(),
+            bucket_mask: buckets - 1,
+            replacement,
+            generation: AtomicU8::new(1),
+            _entry: PhantomData,
+        }
+    }
+
+    /// The number of entries the table can hold.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn capacity(&self) -> usize {
+        self.slots.len()
+    }
+
+    fn bucket(&self, hash: u64) -> &[Slot] {
+        let start = (hash as usize & self.bucket_mask) * BUCKET_SIZE;
+        &self.slots[start..start + BUCKET_SIZE]
+    }
+
+    pub fn probe(&self, hash: u64) -> Option<T> {
+        self.bucket(hash).iter().find_map(|slot| {
+            let data = slot.data.load(Ordering::Relaxed);
+            let key = slot.key.load(Ordering::Relaxed);
+            (generation_of(data) != 0 && key ^ data == hash).then(|| T::unpack(data & ENTRY_MASK))
+        })
+    }
+
+    pub fn store(&self, hash: u64, entry: T) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let bucket = self.bucket(hash);
+        let mut victim = 0;
+        let mut victim_value = i32::MAX;
+
+        for (i, slot) in bucket.iter().enumerate() {
+            let data = slot.data.load(Ordering::Relaxed);
+            let key = slot.key.load(Ordering::Relaxed);
+            let slot_generation = generation_of(data);
+            if slot_generation == 0 {
+                // An empty slot is only worth less than a slot of the same position.
+                if victim_value > i32::MIN {
+                    victim = i;
+                    victim_value = i32::MIN;
+                }
+                continue;
+            }
+
+            if key ^ data == hash {
+                let existing = T::unpack(data & ENTRY_MASK);
+                if self.replacement == Replacement::PriorityPreferred
+                    && slot_generation == generation
+                    && entry.priority() < existing.priority()
+                {
+                    return;
+                }
+                victim = i;
+                break;
+            }
+
+            let value = match self.replacement {
+                Replacement::Always => {
+                    if i == (hash >> 32) as usize % BUCKET_SIZE { -1 } else { 0 }
+                }
+                Replacement::PriorityPreferred => {
+                    let age = generation_distance(slot_generation, generation);
+                    T::unpack(data & ENTRY_MASK).priority() as i32 - AGE_PENALTY * age as i32
+                }
+            };
+            if value < victim_value {
+                victim = i;
+                victim_value = value;
+            }
+        }
+
+        let slot = &bucket[victim];
+        let data = with_generation(entry, generation);
+        slot.data.store(data, Ordering::Relaxed);
+        slot.key.store(hash ^ data, Ordering::Relaxed);
+    }
+
+    /// Overwrites only the data word of the slot holding `hash` with `entry`, leaving the key
+    /// of the earlier store. This is the torn slot two racing stores can leave behind.
+    #[cfg(test)]
+    pub fn tear(&self, hash: u64, entry: T) {
+        let generation = self.generation.load(Ordering::Relaxed);
+        let slot = self
+            .bucket(hash)
+            .iter()
+            .find(|slot| slot.key.load(Ordering::Relaxed) ^ slot.data.load(Ordering::Relaxed) == hash)
+            .expect("no slot holds the hash");
+        slot.data.store(with_generation(entry, generation), Ordering::Relaxed);
+    }
+
+    /// Starts a new generation. Entries written before it are replaced more readily.
+    pub fn new_search(&self) {
+        let _ = self.generation.fetch_update(Ordering::Relaxed, Ordering::Relaxed, |generation| {
+            Some(generation.checked_add(1).unwrap_or(1))
+        });
+    }
+}
+
+fn generation_of(data: u64) -> u8 {
+    (data >> GENERATION_SHIFT) as u8
+}
+
+fn with_generation<T: TableEntry>(entry: T, generation: u8) -> u64 {
+    let data = entry.pack();
+    debug_assert_eq!(data & !ENTRY_MASK, 0, "entries must leave the generation byte free");
+    data | (generation as u64) << GENERATION_SHIFT
+}
+
+/// The number of generations from `from` to `to`, where generations count 1..=255.
+fn generation_distance(from: u8, to: u8) -> u8 {
+    if to >= from {
+        to - from
+    } else {
+        u8::MAX - from + to
+    }
+}
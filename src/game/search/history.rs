@@ -0,0 +1,93 @@
+// src/game/search/history.rs
+
+use shakmaty::{Color, Move};
+
+/// History scores stay within +/- this bound.
+pub const HISTORY_MAX: i32 = 16_384;
+
+const PIECE_SQUARES: usize = 12 * 64;
+
+/// Statistics about quiet moves gathered during the search, used to order them.
+///
+/// - The butterfly table scores a move by its piece and destination square.
+/// - The counter-move table remembers the quiet move that refuted each previous move.
+/// - The continuation table scores a move by its piece and destination square, given the piece
+///   and destination square of the previous move.
+///
+/// Updates use "gravity": an entry moves towards the bound by a share of the bonus that shrinks
+/// as it gets closer, so entries stay bounded and recent results weigh more than old ones.
+#[derive(Clone, Debug)]
+pub struct MoveHistory {
+    butterfly: Vec<i32>,
+    counter_moves: Vec<Option<Move>>,
+    continuation: Vec<i32>,
+}
+
+impl Default for MoveHistory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl MoveHistory {
+    pub fn new() -> Self {
+        Self {
+            butterfly: vec![0; PIECE_SQUARES],
+            counter_moves: vec![None; PIECE_SQUARES],
+            continuation: vec![0; PIECE_SQUARES * PIECE_SQUARES],
+        }
+    }
+
+    /// The bonus for a move that caused a cutoff at `depth`. Moves that were searched before it
+    /// without causing one receive the same amount as a malus.
+    pub fn bonus(depth: u8) -> i32 {
+        (32 * (depth as i32).pow(2)).min(HISTORY_MAX)
+    }
+
+    pub fn butterfly(&self, color: Color, m: &Move) -> i32 {
+        self.butterfly[piece_square(color, m)]
+    }
+
+    pub fn update_butterfly(&mut self, color: Color, m: &Move, bonus: i32) {
+        apply_gravity(&mut self.butterfly[piece_square(color, m)], bonus);
+    }
+
+    /// The continuation score of `m`, played by `color` in reply to `previous`.
+    pub fn continuation(&self, color: Color, m: &Move, previous: &Move) -> i32 {
+        self.continuation[continuation_index(color, m, previous)]
+    }
+
+    pub fn update_continuation(&mut self, color: Color, m: &Move, previous: &Move, bonus: i32) {
+        apply_gravity(&mut self.continuation[continuation_index(color, m, previous)], bonus);
+    }
+
+    /// The quiet move that last refuted `previous`, which was played by the opponent of `color`.
+    pub fn counter_move(&self, color: Color, previous: &Move) -> Option<Move> {
+        self.counter_moves[piece_square(!color, previous)]
+    }
+
+    pub fn set_counter_move(&mut self, color: Color, previous: &Move, m: Move) {
+        self.counter_moves[piece_square(!color, previous)] = Some(m);
+    }
+
+    /// Halves all scores, so that statistics from earlier searches fade out.
+    pub fn age(&mut self) {
+        for entry in self.butterfly.iter_mut().chain(self.continuation.iter_mut()) {
+            *entry /= 2;
+        }
+    }
+}
+
+fn piece_square(color: Color, m: &Move) -> usize {
+    // Shakmaty's Role enum starts at Pawn = 1.
+    (color as usize * 6 + (m.role() as usize - 1)) * 64 + m.to() as usize
+}
+
+fn continuation_index(color: Color, m: &Move, previous: &Move) -> usize {
+    piece_square(!color, previous) * PIECE_SQUARES + piece_square(color, m)
+}
+
+fn apply_gravity(entry: &mut i32, bonus: i32) {
+    let bonus = bonus.clamp(-HISTORY_MAX, HISTORY_MAX);
+    *entry += bonus - *entry * bonus.abs() / HISTORY_MAX;
+}
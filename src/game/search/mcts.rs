@@ -2,20 +2,52 @@
 
 use crate::game::evaluation;
 use crate::game::evaluation::see;
-use crate::game::search::{MoveTreeNode, SearchConfig, Searcher, MctsCache, MctsNodeData};
-use shakmaty::{Chess, Move, Position, EnPassantMode};
-use shakmaty::zobrist::ZobristHash;
+use crate::game::search::history::{MoveHistory, HISTORY_MAX};
+use crate::game::search::{
+    MoveTreeNode, PvsSearcher, Score, SearchAlgorithm, SearchConfig, SearchLimits, SearchResult, SearchStats,
+    SearchTables, Searcher, MctsCache, MctsNodeData,
+};
+use shakmaty::{Chess, Color, Move, Position, EnPassantMode};
+use shakmaty::zobrist::{Zobrist64, ZobristHash};
+use std::sync::atomic::{AtomicU32, Ordering};
 use std::sync::{Arc, Mutex};
 use std::time::Instant;
 
-#[derive(Debug, Clone, Copy, Default)]
-pub struct MctsStats {
-    pub max_depth: u32,
-    pub branches_evaluated: u32,
-}
+/// Policy scores are divided by this before the softmax, so a move that scores this many
+/// centipawns more than another is e times as likely to be tried first.
+const PRIOR_TEMPERATURE_CP: f64 = 100.0;
+/// The policy score of a move with the highest possible history score, in centipawns.
+const PRIOR_HISTORY_CP: i32 = 100;
+/// The history bonus of a move whose simulation was won outright. Lost ones get it as a malus.
+const HISTORY_BONUS: f64 = 256.0;
+/// How many plies below the previous root the next search looks for its position.
+const REUSE_PLIES: usize = 2;
 
+/// Monte Carlo tree search with PUCT selection.
+///
+/// Each simulation descends the tree to a leaf, choosing at every node the move whose average
+/// result plus an exploration term is highest. The exploration term favours moves with a high
+/// prior from a cheap policy and shrinks as they get visited. The leaf is expanded, valued by a
+/// quiescence search, and its value is backed up the path with the perspective flipped at
+/// every ply.
+///
+/// The MCTS-AB hybrid (`SearchAlgorithm::MctsAb`) values the leaves with an alpha-beta search
+/// of `mcts_leaf_depth` plies instead, which sees the short tactics that a quiescence search
+/// misses.
+///
+/// With more than one thread, all threads run simulations on the same tree, which they only
+/// lock to select a leaf and to back up its value. The tree is kept between searches, so when
+/// the next search starts from a position reached by our move and the opponent's reply, it
+/// continues from that subtree.
 pub struct MctsSearcher {
-    mcts_cache: Arc<Mutex<MctsCache>>,
+    mcts_cache: Arc<MctsCache>,
+    /// Values the leaves with its alpha-beta and quiescence searches, sharing the player's
+    /// transposition table and evaluation cache. Helper threads value theirs with clones.
+    leaf_searcher: PvsSearcher,
+    /// Rewards the moves of simulations that went well, for the priors of nodes expanded later.
+    history: MoveHistory,
+    /// The root position and the tree of the last search.
+    previous_tree: Option<(Chess, Node)>,
 }
 
 impl Default for MctsSearcher {
@@ -26,8 +58,15 @@ impl Default for MctsSearcher {
 
 impl MctsSearcher {
     pub fn new() -> Self {
+        Self::with_tables(SearchTables::default())
+    }
+
+    pub fn with_tables(tables: SearchTables) -> Self {
         Self {
-            mcts_cache: Arc::new(Mutex::new(MctsCache::new())),
+            mcts_cache: tables.mcts_cache.clone().unwrap_or_else(|| Arc::new(MctsCache::new())),
+            leaf_searcher: PvsSearcher::with_tables(tables),
+            history: MoveHistory::new(),
+            previous_tree: None,
         }
     }
 }
@@ -36,27 +75,26 @@ impl Searcher for MctsSearcher {
     fn search(
         &mut self,
         pos: &Chess,
-        _depth: u8,
+        history: &[Zobrist64],
+        limits: &SearchLimits,
         config: &SearchConfig,
-        _build_tree: bool,
+        build_tree: bool,
         verbose: bool,
-    ) -> (Option<Move>, i32, Option<MoveTreeNode>, Option<String>) {
-        let (best_move, score, final_tree, stats) = self.mcts(pos, config, verbose);
-        let stats_string = format!(
-            "MCTS Stats: Max Depth={}, Branches Evaluated={}",
-            stats.max_depth, stats.branches_evaluated
-        );
-        (best_move, score, Some(final_tree), Some(stats_string))
+    ) -> SearchResult {
+        self.mcts(pos, history, limits, config, build_tree, verbose)
     }
 }
 
 impl MctsSearcher {
     fn mcts(
-        &self,
+        &mut self,
         pos: &Chess,
+        game_history: &[Zobrist64],
+        limits: &SearchLimits,
         config: &SearchConfig,
+        build_tree: bool,
         verbose: bool,
-    ) -> (Option<Move>, i32, MoveTreeNode, MctsStats) {
+    ) -> SearchResult {
         if verbose {
             let fen = shakmaty::fen::Fen::from_position(pos, EnPassantMode::Legal);
             println!("MCTS evaluation started for position: {fen}");
@@ -67,172 +105,351 @@ impl MctsSearcher {
                 println!("MCTS task finished: Game is already over.");
             }
             let score = evaluation::evaluate(pos, config);
-            return (
-                None,
-                score,
-                MoveTreeNode {
+            return SearchResult {
+                score: Score::Cp(score),
+                tree: Some(MoveTreeNode {
                     move_san: "root".to_string(),
-                    score,
+                    score: Score::Cp(score),
                     children: vec![],
-                },
-                MctsStats::default(),
-            );
+                }),
+                ..Default::default()
+            };
         }
 
-        let mut root = Node::new(pos, None, Arc::clone(&self.mcts_cache));
-        let mut stats = MctsStats::default();
         let start_time = Instant::now();
+        self.history.age();
+        self.mcts_cache.new_search();
+        self.leaf_searcher.start_leaf_searches(pos, limits);
 
-        for iteration_count in 0..config.mcts_simulations {
-            if verbose && iteration_count % 10000 == 0 {
-                let best_child = root.children.iter().max_by(|a, b| a.visits.cmp(&b.visits));
-                let best_move_san = best_child
-                    .and_then(|c| c.parent_move)
-                    .map(|m| shakmaty::san::SanPlus::from_move(pos.clone(), m).to_string())
-                    .unwrap_or_else(|| "N/A".to_string());
-
-                println!(
-                    "MCTS Progress: Branches={}, Time={}s, Current Best Move={}",
-                    stats.branches_evaluated,
-                    start_time.elapsed().as_secs(),
-                    best_move_san
-                );
-            }
-            let mut path_indices = Vec::new();
-            let mut current_pos = pos.clone();
-
-            // Selection
-            let mut current_node = &root;
-            while !current_node.is_leaf() {
-                let best_child_idx = current_node
-                    .children
-                    .iter()
-                    .enumerate()
-                    .max_by(|(_, a), (_, b)| {
-                        let a_ucb1 = a.ucb1(current_node.visits);
-                        let b_ucb1 = b.ucb1(current_node.visits);
-                        a_ucb1.partial_cmp(&b_ucb1).unwrap_or(std::cmp::Ordering::Equal)
-                    })
-                    .map(|(i, _)| i)
-                    .unwrap(); // This is safe because we handle the None case in expand
-                path_indices.push(best_child_idx);
-                current_node = &current_node.children[best_child_idx];
-            }
-
-            // Expansion
-            let mut leaf_node_mut = &mut root;
-            for &idx in &path_indices {
-                let child_move = leaf_node_mut.children[idx].parent_move.unwrap();
-                current_pos.play_unchecked(child_move);
-                leaf_node_mut = &mut leaf_node_mut.children[idx];
-            }
-
-            if leaf_node_mut.visits != 0 && !current_pos.is_game_over() {
-                leaf_node_mut.expand(&current_pos);
-            }
+        let mut root = self
+            .take_subtree(pos)
+            .unwrap_or_else(|| Node::new(None, 1.0, self.mcts_cache.probe(&pos.zobrist_hash(EnPassantMode::Legal))));
+        if root.is_leaf() {
+            let children = child_inputs(&self.leaf_searcher, &self.mcts_cache, pos, config);
+            root.expand(pos.turn(), children, &self.history);
+        }
 
-            let (sim_start_pos, _node_to_sim) = if !leaf_node_mut.children.is_empty() {
-                let random_child_idx = rand::random::<usize>() % leaf_node_mut.children.len();
-                path_indices.push(random_child_idx);
-                let child_node = &leaf_node_mut.children[random_child_idx];
-                let mut sim_pos = current_pos.clone();
-                sim_pos.play_unchecked(child_node.parent_move.unwrap());
-                (sim_pos, child_node)
-            } else {
-                (current_pos, &*leaf_node_mut)
-            };
+        let shared = SharedSearch {
+            tree: Mutex::new(Tree {
+                root,
+                history: std::mem::take(&mut self.history),
+                simulations: 0,
+                depth: 0,
+            }),
+            mcts_cache: &self.mcts_cache,
+            root_pos: pos,
+            game_history,
+            config,
+            limits,
+            exploration: config.mcts_exploration as f64 / 100.0,
+            leaf_search_depth: match config.search_algorithm {
+                SearchAlgorithm::MctsAb => config.mcts_leaf_depth,
+                _ => 0,
+            },
+            budget: limits
+                .nodes
+                .map_or(config.mcts_simulations, |n| config.mcts_simulations.min(n.min(u32::MAX as u64) as u32)),
+            started: AtomicU32::new(0),
+            deadline: limits.hard_deadline(start_time),
+            start_time,
+        };
 
-            // Simulation
-            let mut sim_pos = sim_start_pos;
-            let mut sim_depth = 0;
-            while !sim_pos.is_game_over() && sim_depth < 50 {
-                let moves = sim_pos.legal_moves();
-                if moves.is_empty() {
-                    break;
-                }
-                let m = &moves[rand::random::<usize>() % moves.len()];
-                sim_pos.play_unchecked(*m);
-                sim_depth += 1;
+        let mut helpers: Vec<PvsSearcher> =
+            (1..limits.threads.max(1)).map(|_| self.leaf_searcher.clone()).collect();
+        std::thread::scope(|scope| {
+            for helper in helpers.iter_mut() {
+                let shared = &shared;
+                scope.spawn(move || shared.run(helper, false));
             }
+            shared.run(&mut self.leaf_searcher, verbose);
+        });
 
-            let eval_score = evaluation::evaluate(&sim_pos, config);
-            let win_prob = 1.0 / (1.0 + (-(eval_score as f64) / 400.0).exp());
-
-            // Backpropagation
-            let mut node_to_update = &mut root;
-            node_to_update.visits += 1;
-            node_to_update.wins += win_prob;
-            for &idx in &path_indices {
-                node_to_update = &mut node_to_update.children[idx];
-                node_to_update.visits += 1;
-                node_to_update.wins += win_prob;
-            }
-            stats.branches_evaluated += 1;
-            stats.max_depth = stats.max_depth.max(path_indices.len() as u32);
+        let Tree { root, history, simulations, depth } = shared.tree.into_inner().unwrap();
+        self.history = history;
+        let mut leaf_stats = std::mem::take(&mut self.leaf_searcher.stats);
+        for helper in &helpers {
+            leaf_stats.add_helper(&helper.stats);
         }
+        let stats = SearchStats {
+            depth,
+            seldepth: depth.saturating_add(leaf_stats.seldepth),
+            nodes: simulations + leaf_stats.nodes,
+            qnodes: leaf_stats.qnodes,
+            tt_hits: leaf_stats.tt_hits,
+            tb_hits: leaf_stats.tb_hits,
+            elapsed: start_time.elapsed(),
+            ..Default::default()
+        };
 
         let best_child = root
             .children
             .iter()
             .max_by(|a, b| a.visits.cmp(&b.visits));
 
-        if let Some(best_child) = best_child {
+        let result = if let Some(best_child) = best_child {
             let best_move = best_child.parent_move.unwrap();
-            let final_tree = root.to_move_tree_node(pos);
-            let score = (best_child.wins / best_child.visits as f64 * 100.0) as i32;
-            root.update_cache(pos);
-
             if verbose {
                 let san_move = shakmaty::san::SanPlus::from_move(pos.clone(), best_move);
                 println!("MCTS task finished: Best move found: {san_move}");
             }
 
-            (Some(best_move), score, final_tree, stats)
+            SearchResult {
+                best_move: Some(best_move),
+                score: best_child.score(),
+                pv: root.principal_variation(),
+                tree: build_tree.then(|| root.to_move_tree_node(pos)),
+                stats,
+            }
         } else {
             if verbose {
                 println!("MCTS task finished: No best move found.");
             }
-            (
-                None,
-                0,
-                MoveTreeNode {
+            SearchResult {
+                tree: Some(MoveTreeNode {
                     move_san: "root".to_string(),
-                    score: 0,
+                    score: Score::default(),
                     children: vec![],
-                },
+                }),
                 stats,
-            )
+                ..Default::default()
+            }
+        };
+
+        root.update_cache(pos, &self.mcts_cache);
+        self.previous_tree = Some((pos.clone(), root));
+        result
+    }
+
+    /// Takes the part of the previous tree that starts at `pos`: the whole tree if the same
+    /// position is searched again, or the subtree of a position up to `REUSE_PLIES` plies below
+    /// its root, such as the one after our move and the opponent's reply. The rest is dropped.
+    fn take_subtree(&mut self, pos: &Chess) -> Option<Node> {
+        let (previous_pos, previous_root) = self.previous_tree.take()?;
+        let hash: Zobrist64 = pos.zobrist_hash(EnPassantMode::Legal);
+        let mut candidates = vec![(previous_pos, previous_root)];
+        for ply in 0..=REUSE_PLIES {
+            let mut next = Vec::new();
+            for (candidate_pos, candidate) in candidates {
+                if candidate_pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal) == hash {
+                    return Some(Node { parent_move: None, prior: 1.0, ..candidate });
+                }
+                if ply < REUSE_PLIES {
+                    for child in candidate.children {
+                        let mut child_pos = candidate_pos.clone();
+                        child_pos.play_unchecked(child.parent_move.unwrap());
+                        next.push((child_pos, child));
+                    }
+                }
+            }
+            candidates = next;
+        }
+        None
+    }
+}
+
+/// The state the threads of one search share.
+struct SharedSearch<'a> {
+    tree: Mutex<Tree>,
+    mcts_cache: &'a MctsCache,
+    root_pos: &'a Chess,
+    /// Hashes of the positions before the root since the last irreversible move, oldest first.
+    game_history: &'a [Zobrist64],
+    config: &'a SearchConfig,
+    limits: &'a SearchLimits,
+    exploration: f64,
+    leaf_search_depth: u8,
+    /// The most simulations to run, counted over all threads.
+    budget: u32,
+    /// Simulations started so far.
+    started: AtomicU32,
+    deadline: Option<Instant>,
+    start_time: Instant,
+}
+
+struct Tree {
+    root: Node,
+    history: MoveHistory,
+    /// Simulations completed.
+    simulations: u64,
+    /// The deepest leaf reached.
+    depth: u8,
+}
+
+impl SharedSearch<'_> {
+    /// Runs simulations until the budget or the time is spent or the search is stopped. The
+    /// first simulation always runs, so that a move is available.
+    fn run(&self, leaf_searcher: &mut PvsSearcher, verbose: bool) {
+        loop {
+            let started = self.started.fetch_add(1, Ordering::Relaxed);
+            if started >= self.budget {
+                break;
+            }
+            if started > 0
+                && (self.limits.is_stopped() || self.deadline.is_some_and(|d| Instant::now() >= d))
+            {
+                break;
+            }
+            if verbose && started.is_multiple_of(10000) {
+                self.report_progress(started);
+            }
+            if !self.simulate(leaf_searcher) {
+                break;
+            }
         }
     }
+
+    /// Runs one simulation. Returns `false` if the leaf search was stopped midway.
+    ///
+    /// The leaf is valued and the policy inputs of its children computed without holding the
+    /// lock, so other threads run their simulations in the meantime.
+    fn simulate(&self, leaf_searcher: &mut PvsSearcher) -> bool {
+        let (path, moves) = self.tree.lock().unwrap().root.select_path(self.exploration);
+        let mut leaf_pos = self.root_pos.clone();
+        let mut path_hashes = Vec::with_capacity(moves.len());
+        for m in moves {
+            path_hashes.push(leaf_pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0);
+            leaf_pos.play_unchecked(m);
+        }
+
+        // The value is the result for the side to move at the leaf.
+        let earlier = self.game_history.iter().map(|hash| hash.0).chain(path_hashes);
+        let (value, children) = match terminal_value(&leaf_pos, earlier, self.draw_value(&leaf_pos)) {
+            Some(value) => (value, Vec::new()),
+            None => {
+                let children = child_inputs(leaf_searcher, self.mcts_cache, &leaf_pos, self.config);
+                match leaf_searcher.leaf_value(&leaf_pos, self.leaf_search_depth, self.config) {
+                    Some(score) => (Score::from_search(score).win_probability(), children),
+                    None => {
+                        // The search was stopped midway, so its score is meaningless.
+                        self.tree.lock().unwrap().root.revert_visits(&path);
+                        return false;
+                    }
+                }
+            }
+        };
+
+        let mut tree = self.tree.lock().unwrap();
+        let Tree { root, history, simulations, depth } = &mut *tree;
+        let leaf = root.descend_mut(&path);
+        // Another thread may have expanded the leaf since it was selected.
+        if leaf.is_leaf() {
+            leaf.expand(leaf_pos.turn(), children, history);
+        }
+        root.backpropagate(&path, value, self.root_pos.turn(), history);
+        *simulations += 1;
+        *depth = (*depth).max(path.len().min(u8::MAX as usize) as u8);
+        true
+    }
+
+    /// The value of a drawn leaf for its side to move. With a positive contempt factor the
+    /// root side treats draws as slightly losing, as in the PVS search.
+    fn draw_value(&self, pos: &Chess) -> f64 {
+        let contempt = self.config.contempt_factor;
+        let cp = if pos.turn() == self.root_pos.turn() { -contempt } else { contempt };
+        Score::Cp(cp).win_probability()
+    }
+
+    fn report_progress(&self, started: u32) {
+        let tree = self.tree.lock().unwrap();
+        let best_child = tree.root.children.iter().max_by(|a, b| a.visits.cmp(&b.visits));
+        let best_move_san = best_child
+            .and_then(|c| c.parent_move)
+            .map(|m| shakmaty::san::SanPlus::from_move(self.root_pos.clone(), m).to_string())
+            .unwrap_or_else(|| "N/A".to_string());
+
+        println!(
+            "MCTS Progress: Simulations={}, Time={}s, Current Best Move={}",
+            started,
+            self.start_time.elapsed().as_secs(),
+            best_move_san
+        );
+    }
+}
+
+/// What a new child node starts with: its move, its policy score without the history term, and
+/// the statistics of its position from earlier searches.
+type ChildInput = (Move, f64, Option<MctsNodeData>);
+
+/// The inputs for the children of `pos`. The policy score is what the move gains by static
+/// evaluation plus what it can lose in the exchange on its destination square by SEE.
+fn child_inputs(leaf_searcher: &PvsSearcher, mcts_cache: &MctsCache, pos: &Chess, config: &SearchConfig) -> Vec<ChildInput> {
+    let eval = leaf_searcher.evaluate_with_cache(pos, config);
+    pos.legal_moves()
+        .into_iter()
+        .map(|m| {
+            let mut new_pos = pos.clone();
+            new_pos.play_unchecked(m);
+            let gain = -leaf_searcher.evaluate_with_cache(&new_pos, config) - eval;
+            // SEE cannot see the pawn taken en passant, and castling moves no piece onto the
+            // square it names.
+            let exchange = match m {
+                Move::Normal { from, to, .. } => see::see(pos.board(), from, to).min(0),
+                _ => 0,
+            };
+            let seed = mcts_cache.probe(&new_pos.zobrist_hash(EnPassantMode::Legal));
+            (m, (gain + exchange) as f64, seed)
+        })
+        .collect()
+}
+
+/// The result of `pos` for the side to move if the game is over: 0 when checkmated, and
+/// `draw_value` for stalemate, insufficient material, the fifty-move rule or a repetition.
+/// `earlier` holds the hashes of the positions before `pos`, oldest first.
+fn terminal_value(pos: &Chess, earlier: impl DoubleEndedIterator<Item = u64>, draw_value: f64) -> Option<f64> {
+    if pos.is_checkmate() {
+        Some(0.0)
+    } else if pos.is_stalemate()
+        || pos.is_insufficient_material()
+        || pos.halfmoves() >= 100
+        || is_repetition(pos, earlier)
+    {
+        Some(draw_value)
+    } else {
+        None
+    }
+}
+
+/// Whether `pos` already occurred among the `earlier` positions. Like the PVS search, this
+/// scores the first repetition as a draw. Only positions since the last capture or pawn move
+/// can repeat, and the same side has to be to move.
+fn is_repetition(pos: &Chess, earlier: impl DoubleEndedIterator<Item = u64>) -> bool {
+    let hash = pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal).0;
+    earlier
+        .rev()
+        .skip(1)
+        .step_by(2)
+        .take(pos.halfmoves() as usize / 2)
+        .any(|earlier| earlier == hash)
+}
+
+fn softmax(scores: &[f64]) -> Vec<f64> {
+    let max = scores.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let exps: Vec<f64> = scores.iter().map(|score| (score - max).exp()).collect();
+    let sum: f64 = exps.iter().sum();
+    exps.into_iter().map(|e| e / sum).collect()
 }
 
 #[derive(Debug, Clone)]
 struct Node {
     parent_move: Option<Move>,
+    /// The probability the policy gave `parent_move`.
+    prior: f64,
+    /// Includes the simulations through this node that are still running.
     visits: u32,
+    /// The sum of the results, between 0 and 1, for the side that played `parent_move`.
     wins: f64,
     children: Vec<Node>,
-    mcts_cache: Arc<Mutex<MctsCache>>,
 }
 
 impl Node {
-    fn new(pos: &Chess, parent_move: Option<Move>, mcts_cache: Arc<Mutex<MctsCache>>) -> Self {
-        let hash = pos.zobrist_hash::<crate::game::search::evaluation_cache::Zobrist64>(EnPassantMode::Legal);
-        let (visits, wins) = {
-            let cache = mcts_cache.lock().unwrap();
-            if let Some(data) = cache.probe(&hash) {
-                (data.visits, data.wins)
-            } else {
-                (0, 0.0)
-            }
-        };
-
+    fn new(parent_move: Option<Move>, prior: f64, seed: Option<MctsNodeData>) -> Self {
+        let seed = seed.unwrap_or(MctsNodeData { visits: 0, wins: 0.0 });
         Self {
             parent_move,
-            visits,
-            wins,
+            prior,
+            visits: seed.visits,
+            wins: seed.wins,
             children: Vec::new(),
-            mcts_cache,
         }
     }
 
@@ -240,33 +457,116 @@ impl Node {
         self.children.is_empty()
     }
 
-    fn expand(&mut self, pos: &Chess) {
-        if !self.children.is_empty() {
-            return;
+    fn descend_mut(&mut self, path: &[usize]) -> &mut Node {
+        path.iter().fold(self, |node, &idx| &mut node.children[idx])
+    }
+
+    /// Adds the children of this node, where `turn` is to move. Their priors are a softmax over
+    /// their policy scores plus their history scores.
+    fn expand(&mut self, turn: Color, children: Vec<ChildInput>, history: &MoveHistory) {
+        let scores: Vec<f64> = children
+            .iter()
+            .map(|(m, score, _)| {
+                let history = history.butterfly(turn, m) * PRIOR_HISTORY_CP / HISTORY_MAX;
+                (score + history as f64) / PRIOR_TEMPERATURE_CP
+            })
+            .collect();
+        self.children = children
+            .into_iter()
+            .zip(softmax(&scores))
+            .map(|((m, _, seed), prior)| Node::new(Some(m), prior, seed))
+            .collect();
+    }
+
+    /// Descends from this node to a leaf, and returns the path to the leaf and its moves.
+    ///
+    /// The nodes on the path count the visit at once, which scores it as a loss for their side
+    /// until the result is backed up. This virtual loss steers the other threads to other lines
+    /// instead of all searching the same one.
+    fn select_path(&mut self, exploration: f64) -> (Vec<usize>, Vec<Move>) {
+        let mut path = Vec::new();
+        let mut moves = Vec::new();
+        let mut node = self;
+        node.visits += 1;
+        while !node.is_leaf() {
+            let idx = node.select_child(exploration);
+            node = &mut node.children[idx];
+            node.visits += 1;
+            moves.push(node.parent_move.unwrap());
+            path.push(idx);
         }
-        for m in pos.legal_moves() {
-            if m.is_capture() {
-                if let Some(from) = m.from() {
-                    if see::see(pos.board(), from, m.to()) < 0 {
-                        continue; // Prune losing captures
-                    }
-                }
-            }
-            let mut new_pos = pos.clone();
-            new_pos.play_unchecked(m);
-            self.children.push(Node::new(&new_pos, Some(m), Arc::clone(&self.mcts_cache)));
+        (path, moves)
+    }
+
+    /// Takes back the visits `select_path` counted for a simulation that did not finish.
+    fn revert_visits(&mut self, path: &[usize]) {
+        let mut node = self;
+        node.visits -= 1;
+        for &idx in path {
+            node = &mut node.children[idx];
+            node.visits -= 1;
         }
     }
 
-    fn ucb1(&self, parent_visits: u32) -> f64 {
-        if self.visits == 0 {
-            f64::INFINITY
+    /// Adds the result of a simulation to the nodes on `path`, whose visits `select_path` has
+    /// already counted. `value` is the result for the side to move at the leaf, and each node
+    /// keeps the results of the side that moved into it, so the value flips at every ply. The
+    /// moves on the path get a history bonus or malus for their side.
+    fn backpropagate(&mut self, path: &[usize], value: f64, root_turn: Color, history: &mut MoveHistory) {
+        let result_at = |depth: usize| {
+            if (path.len() - depth).is_multiple_of(2) { 1.0 - value } else { value }
+        };
+        let mut node = self;
+        node.wins += result_at(0);
+        for (depth, &idx) in path.iter().enumerate() {
+            node = &mut node.children[idx];
+            let result = result_at(depth + 1);
+            node.wins += result;
+            let mover = if depth.is_multiple_of(2) { root_turn } else { !root_turn };
+            let bonus = ((result - 0.5) * 2.0 * HISTORY_BONUS) as i32;
+            history.update_butterfly(mover, &node.parent_move.unwrap(), bonus);
+        }
+    }
+
+    /// The child with the highest PUCT score: its average result, plus the exploration
+    /// constant times its prior, scaled by the square root of this node's visits over its own.
+    fn select_child(&self, exploration: f64) -> usize {
+        let sqrt_visits = (self.visits.max(1) as f64).sqrt();
+        // Unvisited children are expected to do as well for their side as this node does.
+        let first_play = if self.visits > 0 { 1.0 - self.wins / self.visits as f64 } else { 0.5 };
+        let puct = |child: &Node| {
+            let average = if child.visits > 0 { child.wins / child.visits as f64 } else { first_play };
+            average + exploration * child.prior * sqrt_visits / (1 + child.visits) as f64
+        };
+        self.children
+            .iter()
+            .enumerate()
+            .max_by(|(_, a), (_, b)| puct(a).total_cmp(&puct(b)))
+            .map(|(i, _)| i)
+            .unwrap() // Only called on nodes with children
+    }
+
+    /// The average result of this node as a score for the side that played `parent_move`.
+    fn score(&self) -> Score {
+        if self.visits > 0 {
+            Score::from_win_probability(self.wins / self.visits as f64)
         } else {
-            (self.wins / self.visits as f64)
-                + (2.0f64.ln() * parent_visits as f64 / self.visits as f64).sqrt()
+            Score::default()
         }
     }
 
+    /// Follows the most visited child from this node down to a leaf.
+    fn principal_variation(&self) -> Vec<Move> {
+        let mut pv = Vec::new();
+        let mut node = self;
+        while let Some(child) = node.children.iter().filter(|c| c.visits > 0).max_by_key(|c| c.visits) {
+            pv.extend(child.parent_move);
+            node = child;
+        }
+        pv
+    }
+
+    /// The visited part of the tree below this node.
     fn to_move_tree_node(&self, parent_pos: &Chess) -> MoveTreeNode {
         let mut current_pos = parent_pos.clone();
         if let Some(m) = self.parent_move {
@@ -278,23 +578,85 @@ impl Node {
                 .parent_move
                 .map(|m| shakmaty::san::SanPlus::from_move(parent_pos.clone(), m).to_string())
                 .unwrap_or_else(|| "root".to_string()),
-            score: if self.visits > 0 { (self.wins / self.visits as f64 * 100.0) as i32 } else { 0 },
+            score: match self.parent_move {
+                Some(_) => self.score(),
+                // The root keeps the results of the side that moved into it, not the side to move.
+                None if self.visits > 0 => Score::from_win_probability(1.0 - self.wins / self.visits as f64),
+                None => Score::default(),
+            },
             children: self
                 .children
                 .iter()
+                .filter(|c| c.visits > 0)
                 .map(|c| c.to_move_tree_node(&current_pos))
                 .collect(),
         }
     }
 
-    fn update_cache(&self, pos: &Chess) {
-        let mut cache = self.mcts_cache.lock().unwrap();
-        let hash = pos.zobrist_hash::<crate::game::search::evaluation_cache::Zobrist64>(EnPassantMode::Legal);
-        cache.store(hash, MctsNodeData { visits: self.visits, wins: self.wins });
-        for child in &self.children {
+    /// Stores the statistics of this node, at `pos`, and of the visited nodes below it.
+    fn update_cache(&self, pos: &Chess, mcts_cache: &MctsCache) {
+        mcts_cache.store(pos.zobrist_hash(EnPassantMode::Legal), MctsNodeData { visits: self.visits, wins: self.wins });
+        for child in self.children.iter().filter(|c| c.visits > 0) {
             let mut new_pos = pos.clone();
             new_pos.play_unchecked(child.parent_move.unwrap());
-            child.update_cache(&new_pos);
+            child.update_cache(&new_pos, mcts_cache);
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// The most visited child of `node`, with its move.
+    fn most_visited(node: &Node) -> (Move, &Node) {
+        let child = node.children.iter().max_by_key(|c| c.visits).unwrap();
+        (child.parent_move.unwrap(), child)
+    }
+
+    #[test]
+    fn test_virtual_loss_spreads_simulations_over_children() {
+        let pos = Chess::default();
+        let mut root = Node::new(None, 1.0, None);
+        let children = pos.legal_moves().into_iter().map(|m| (m, 0.0, None)).collect();
+        root.expand(pos.turn(), children, &MoveHistory::new());
+
+        let (first, _) = root.select_path(1.5);
+        let (second, _) = root.select_path(1.5);
+        assert_ne!(first, second);
+        assert_eq!(root.visits, 2);
+
+        root.revert_visits(&first);
+        root.revert_visits(&second);
+        assert_eq!(root.visits, 0);
+        assert!(root.children.iter().all(|c| c.visits == 0));
+    }
+
+    #[test]
+    fn test_tree_is_reused_after_the_opponent_replies() {
+        let mut searcher = MctsSearcher::new();
+        let config = SearchConfig {
+            search_algorithm: SearchAlgorithm::Mcts,
+            mcts_simulations: 400,
+            ..SearchConfig::default()
+        };
+        let pos = Chess::default();
+        searcher.search(&pos, &[], &SearchLimits::depth(1), &config, false, false);
+
+        let (_, root) = searcher.previous_tree.as_ref().unwrap();
+        let (first, first_node) = most_visited(root);
+        let (reply, reply_node) = most_visited(first_node);
+        let reused_visits = reply_node.visits;
+        assert!(reused_visits > 0);
+
+        let mut next = pos.clone();
+        next.play_unchecked(first);
+        next.play_unchecked(reply);
+        let subtree = searcher.take_subtree(&next).unwrap();
+        assert_eq!(subtree.visits, reused_visits);
+        assert_eq!(subtree.parent_move, None);
+
+        // The previous tree is gone once it was looked at.
+        assert!(searcher.take_subtree(&next).is_none());
+    }
+}
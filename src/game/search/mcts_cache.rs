@@ -1,30 +1,82 @@
+// src/game/search/mcts_cache.rs
+
 use serde::{Deserialize, Serialize};
 use shakmaty::zobrist::Zobrist64;
-use std::collections::HashMap;
+use super::hash_table::{HashTable, Replacement, TableEntry};
+use crate::constants::MCTS_CACHE_MB;
+
+/// The most visits a cache entry records.
+const MAX_VISITS: u32 = (1 << 24) - 1;
 
-#[derive(Clone, Copy, Debug, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
 pub struct MctsNodeData {
     pub visits: u32,
     pub wins: f64,
 }
 
-#[derive(Clone, Debug, Serialize, Deserialize, Default)]
+impl TableEntry for MctsNodeData {
+    /// Visits in bits 32-55 and the wins as an `f32` in the low half. Past `MAX_VISITS`, both
+    /// are scaled down so that the win rate is kept.
+    fn pack(self) -> u64 {
+        let visits = self.visits.min(MAX_VISITS);
+        let wins = self.wins * visits as f64 / self.visits.max(1) as f64;
+        (visits as u64) << 32 | (wins as f32).to_bits() as u64
+    }
+
+    fn unpack(data: u64) -> Self {
+        Self {
+            visits: (data >> 32) as u32,
+            wins: f32::from_bits(data as u32) as f64,
+        }
+    }
+
+    /// The number of binary digits of the visit count, so that well explored positions are
+    /// kept over ones that were only visited a few times.
+    fn priority(&self) -> u8 {
+        (u32::BITS - self.visits.leading_zeros()) as u8
+    }
+}
+
+/// Visit statistics of positions from earlier MCTS searches, which seed the nodes of later
+/// ones. The table has a fixed size, so it does not grow over a long run.
 pub struct MctsCache {
-    table: HashMap<u64, MctsNodeData>,
+    table: HashTable<MctsNodeData>,
+}
+
+impl Default for MctsCache {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl MctsCache {
     pub fn new() -> Self {
+        Self::with_size_mb(MCTS_CACHE_MB)
+    }
+
+    pub fn with_size_mb(size_mb: usize) -> Self {
         Self {
-            table: HashMap::new(),
+            table: HashTable::with_size_mb(size_mb, Replacement::PriorityPreferred),
         }
     }
 
+    /// The number of entries the cache can hold.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn capacity(&self) -> usize {
+        self.table.capacity()
+    }
+
     pub fn probe(&self, hash: &Zobrist64) -> Option<MctsNodeData> {
-        self.table.get(&hash.0).copied()
+        self.table.probe(hash.0)
     }
 
-    pub fn store(&mut self, hash: Zobrist64, data: MctsNodeData) {
-        self.table.insert(hash.0, data);
+    pub fn store(&self, hash: Zobrist64, data: MctsNodeData) {
+        self.table.store(hash.0, data);
     }
-}
\ No newline at end of file
+
+    /// Called at the start of each search, so that statistics of older searches are replaced
+    /// first.
+    pub fn new_search(&self) {
+        self.table.new_search();
+    }
+}
@@ -0,0 +1,811 @@
+//! Unit tests for the search.
+
+use super::*;
+use super::history::{MoveHistory, HISTORY_MAX};
+use super::evaluation_cache::EvaluationCache;
+use super::hash_table::{HashTable, Replacement, TableEntry};
+use super::transposition_table::{Bound, TranspositionTable, TtEntry, score_from_tt, score_to_tt};
+use crate::constants::{MATE_SCORE, MAX_WIN_PROBABILITY_CP, MIN_TABLE_MB, TB_WIN_SCORE};
+use shakmaty::{fen::Fen, CastlingMode, Chess, Square};
+use shakmaty::zobrist::Zobrist64;
+
+fn position(fen: &str) -> Chess {
+    let fen: Fen = fen.parse().unwrap();
+    fen.into_position(CastlingMode::Standard).unwrap()
+}
+
+fn new_searcher() -> PvsSearcher {
+    PvsSearcher::with_tables(SearchTables::with_size_mb(4))
+}
+
+#[test]
+fn test_tt_store_and_probe() {
+    let tt = TranspositionTable::with_size_mb(1);
+    let hash = Zobrist64(0xdead_beef);
+    tt.store(hash, TtEntry { depth: 4, score: 42, bound: Bound::Exact, best_move: None });
+
+    let entry = tt.probe(&hash).unwrap();
+    assert_eq!(entry.depth, 4);
+    assert_eq!(entry.score, 42);
+    assert_eq!(entry.bound, Bound::Exact);
+    assert!(tt.probe(&Zobrist64(0xdead_beef + 1)).is_none());
+}
+
+#[test]
+fn test_tt_prefers_deeper_entries_for_same_position() {
+    let tt = TranspositionTable::with_size_mb(1);
+    let hash = Zobrist64(7);
+    tt.store(hash, TtEntry { depth: 6, score: 10, bound: Bound::Lower, best_move: None });
+    tt.store(hash, TtEntry { depth: 2, score: -10, bound: Bound::Upper, best_move: None });
+    assert_eq!(tt.probe(&hash).unwrap().depth, 6);
+
+    // Entries from an earlier search give way to shallower ones.
+    tt.new_search();
+    tt.store(hash, TtEntry { depth: 2, score: -10, bound: Bound::Upper, best_move: None });
+    assert_eq!(tt.probe(&hash).unwrap().depth, 2);
+}
+
+#[test]
+fn test_tt_entries_survive_packing() {
+    let pos = position("r3k2r/1P6/8/3pP3/8/8/8/R3K2R w KQkq d6 0 1");
+    let moves = ["b7a8q", "b7b8n", "e5d6", "e1g1", "e1c1", "a1a8", "e1d2"];
+    for (i, uci) in moves.iter().enumerate() {
+        let entry = TtEntry {
+            depth: i as u8 * 20,
+            score: if i % 2 == 0 { -MATE_SCORE + i as i32 } else { 1234 },
+            bound: [Bound::Exact, Bound::Lower, Bound::Upper][i % 3],
+            best_move: Some(uci_move(&pos, uci)),
+        };
+        assert_eq!(TtEntry::unpack(entry.pack()), entry, "{uci}");
+    }
+    let empty = TtEntry { depth: 0, score: -1, bound: Bound::Exact, best_move: None };
+    assert_eq!(TtEntry::unpack(empty.pack()), empty);
+}
+
+#[test]
+fn test_torn_slot_reads_as_a_miss() {
+    let table: HashTable<i32> = HashTable::with_size_mb(1, Replacement::Always);
+    let hash = 0x1234_5678_9ABC_DEF0;
+    table.store(hash, 100);
+    assert_eq!(table.probe(hash), Some(100));
+
+    // The data of a racing store differs from the first only in its low byte.
+    table.tear(hash, 101);
+    assert_eq!(table.probe(hash), None);
+}
+
+#[test]
+fn test_packed_entries_leave_the_generation_byte_free() {
+    let deepest = TtEntry { depth: u8::MAX, score: -MATE_SCORE, bound: Bound::Upper, best_move: None };
+    assert_eq!(deepest.pack() >> 56, 0);
+    assert_eq!(TtEntry::unpack(deepest.pack()), deepest);
+
+    let busiest = MctsNodeData { visits: u32::MAX, wins: u32::MAX as f64 / 2.0 };
+    assert_eq!(busiest.pack() >> 56, 0);
+    let unpacked = MctsNodeData::unpack(busiest.pack());
+    assert_eq!(unpacked.wins / unpacked.visits as f64, 0.5);
+}
+
+#[test]
+fn test_hash_table_size_is_a_power_of_two() {
+    let table: HashTable<i32> = HashTable::with_size_mb(3, Replacement::Always);
+    assert!(table.capacity().is_power_of_two());
+    assert!(table.capacity() * 16 <= 3 << 20);
+    assert!(table.capacity() * 16 * 2 > 3 << 20);
+}
+
+#[test]
+fn test_small_budgets_keep_every_table_usable() {
+    let smallest: HashTable<i32> = HashTable::with_size_mb(MIN_TABLE_MB, Replacement::Always);
+    for size_mb in [0, 1, 3] {
+        let tables = SearchTables::with_size_mb(size_mb);
+        assert_eq!(tables.evaluation_cache.capacity(), smallest.capacity(), "{size_mb} MB");
+        assert!(tables.transposition_table.capacity() >= smallest.capacity(), "{size_mb} MB");
+    }
+    let tables = SearchTables::with_size_mb(16);
+    assert!(tables.evaluation_cache.capacity() > smallest.capacity());
+}
+
+#[test]
+fn test_mcts_cache_counts_against_the_tables_size() {
+    assert!(SearchTables::for_algorithm(&SearchAlgorithm::Pvs, 16).mcts_cache.is_none());
+    for algorithm in [SearchAlgorithm::Mcts, SearchAlgorithm::MctsAb] {
+        let tables = SearchTables::for_algorithm(&algorithm, 16);
+        let entries = tables.evaluation_cache.capacity()
+            + tables.transposition_table.capacity()
+            + tables.mcts_cache.as_ref().unwrap().capacity();
+        // Every entry takes 16 bytes.
+        assert!(entries * 16 <= 16 << 20, "{algorithm:?}");
+    }
+}
+
+#[test]
+fn test_hash_table_replaces_shallowest_entry_of_full_bucket() {
+    // A 64-byte table has a single bucket of four entries, so all hashes collide.
+    let tt = TranspositionTable::with_size_mb(0);
+    let hash = |n: u64| Zobrist64(n << 32);
+    for (n, depth) in [(1, 5), (2, 3), (3, 7), (4, 6)] {
+        tt.store(hash(n), TtEntry { depth, score: 0, bound: Bound::Exact, best_move: None });
+    }
+    tt.store(hash(5), TtEntry { depth: 1, score: 0, bound: Bound::Exact, best_move: None });
+    assert!(tt.probe(&hash(2)).is_none());
+    for n in [1, 3, 4, 5] {
+        assert!(tt.probe(&hash(n)).is_some(), "{n}");
+    }
+}
+
+#[test]
+fn test_mcts_cache_keeps_well_visited_positions() {
+    let data = MctsNodeData { visits: 1234, wins: 617.5 };
+    assert_eq!(MctsNodeData::unpack(data.pack()), data);
+
+    // A single bucket, as above: the least visited position makes room.
+    let cache = MctsCache::with_size_mb(0);
+    let hash = |n: u64| Zobrist64(n << 32);
+    for (n, visits) in [(1, 500), (2, 3), (3, 80), (4, 1000)] {
+        cache.store(hash(n), MctsNodeData { visits, wins: 0.0 });
+    }
+    cache.store(hash(5), MctsNodeData { visits: 40, wins: 0.0 });
+    assert!(cache.probe(&hash(2)).is_none());
+    for n in [1, 3, 4, 5] {
+        assert!(cache.probe(&hash(n)).is_some(), "{n}");
+    }
+}
+
+#[test]
+fn test_evaluation_cache_is_shared_between_threads() {
+    let cache = EvaluationCache::with_size_mb(1);
+    std::thread::scope(|scope| {
+        for t in 0..4u64 {
+            let cache = &cache;
+            scope.spawn(move || {
+                for i in 0..1_000u64 {
+                    let hash = i * 4 + t;
+                    cache.store(Zobrist64(hash), hash as i32);
+                }
+            });
+        }
+    });
+    for hash in [0, 1, 2, 3, 3_999] {
+        assert_eq!(cache.probe(&Zobrist64(hash)), Some(hash as i32));
+    }
+}
+
+#[test]
+fn test_tt_mate_scores_are_ply_relative() {
+    let mated_in_five_from_root = -MATE_SCORE + 5;
+    let stored = score_to_tt(mated_in_five_from_root, 3);
+    assert_eq!(stored, -MATE_SCORE + 2);
+    assert_eq!(score_from_tt(stored, 3), mated_in_five_from_root);
+    assert_eq!(score_from_tt(stored, 1), -MATE_SCORE + 3);
+    assert_eq!(score_to_tt(150, 7), 150);
+}
+
+#[test]
+fn test_pvs_finds_back_rank_mate() {
+    let pos = position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    let mut searcher = new_searcher();
+    let config = SearchConfig::default();
+    let result = searcher.search(&pos, &[], &SearchLimits::depth(3), &config, false, false);
+    let best_move = result.best_move.unwrap();
+    assert_eq!(best_move.from(), Some(Square::A1));
+    assert_eq!(best_move.to(), Square::A8);
+    assert_eq!(result.score, Score::Mate(1));
+}
+
+#[test]
+fn test_pvs_plays_a_tablebase_root_move() {
+    let tablebases = Arc::new(Tablebases::open("test-data/syzygy").unwrap());
+    let pos = position("8/8/8/3k4/8/8/8/K6R w - - 0 1");
+    let root_moves = tablebases.root_moves(&pos).unwrap();
+    let tables = SearchTables { tablebases: Some(tablebases), ..SearchTables::with_size_mb(4) };
+    let mut searcher = PvsSearcher::with_tables(tables);
+    let result = searcher.search(&pos, &[], &SearchLimits::depth(4), &SearchConfig::default(), false, false);
+    assert!(result.stats.tb_hits > 0);
+    assert!(root_moves.contains(&result.best_move.unwrap()));
+}
+
+#[test]
+fn test_pvs_stores_root_best_move_in_tt() {
+    let pos = position("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+    let mut searcher = new_searcher();
+    let config = SearchConfig::default();
+    let best_move = searcher.search(&pos, &[], &SearchLimits::depth(3), &config, false, false).best_move;
+
+    let hash = pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal);
+    let entry = searcher.probe_tt(&hash).unwrap();
+    assert_eq!(entry.depth, 3);
+    assert_eq!(entry.best_move, best_move);
+}
+
+#[test]
+fn test_iterative_deepening_respects_movetime() {
+    let pos = Chess::default();
+    let mut searcher = new_searcher();
+    let config = SearchConfig::default();
+    let limits = SearchLimits {
+        movetime: Some(Duration::from_millis(200)),
+        ..SearchLimits::depth(30)
+    };
+    let start = Instant::now();
+    let best_move = searcher.search(&pos, &[], &limits, &config, false, false).best_move;
+    assert!(best_move.is_some());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_iterative_deepening_respects_node_budget() {
+    let pos = Chess::default();
+    let mut searcher = new_searcher();
+    let config = SearchConfig::default();
+    let limits = SearchLimits {
+        nodes: Some(2_000),
+        ..SearchLimits::depth(30)
+    };
+    let best_move = searcher.search(&pos, &[], &limits, &config, false, false).best_move;
+    assert!(best_move.is_some());
+    assert!(searcher.completed_depth < 30);
+}
+
+#[test]
+fn test_stop_flag_cancels_search_and_keeps_best_move() {
+    let pos = Chess::default();
+    let mut searcher = new_searcher();
+    let config = SearchConfig::default();
+    let stop = Arc::new(AtomicBool::new(false));
+    let limits = SearchLimits {
+        stop: Some(stop.clone()),
+        ..SearchLimits::depth(30)
+    };
+
+    let stopper = std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(300));
+        stop.store(true, Ordering::Relaxed);
+    });
+    let start = Instant::now();
+    let best_move = searcher.search(&pos, &[], &limits, &config, false, false).best_move;
+    stopper.join().unwrap();
+
+    assert!(best_move.is_some());
+    assert!(start.elapsed() < Duration::from_secs(5));
+}
+
+#[test]
+fn test_score_mate_conversion_round_trips() {
+    assert_eq!(Score::from_search(MATE_SCORE - 1), Score::Mate(1));
+    assert_eq!(Score::from_search(MATE_SCORE - 5), Score::Mate(3));
+    assert_eq!(Score::from_search(-MATE_SCORE + 2), Score::Mate(-1));
+    assert_eq!(Score::from_search(35), Score::Cp(35));
+    for raw in [MATE_SCORE - 1, MATE_SCORE - 7, -MATE_SCORE + 4, -120] {
+        assert_eq!(Score::from_search(raw).centipawns(), raw);
+    }
+}
+
+#[test]
+fn test_tablebase_scores_are_capped() {
+    assert_eq!(Score::from_search(TB_WIN_SCORE - 3), Score::Cp(MAX_WIN_PROBABILITY_CP));
+    assert_eq!(Score::from_search(-TB_WIN_SCORE + 4), Score::Cp(-MAX_WIN_PROBABILITY_CP));
+    assert_eq!(Score::from_search(TB_WIN_SCORE - 3).after_move(), Score::Cp(-MAX_WIN_PROBABILITY_CP));
+}
+
+#[test]
+fn test_score_after_move_is_for_the_opponent() {
+    assert_eq!(Score::Cp(35).after_move(), Score::Cp(-35));
+    assert_eq!(Score::Mate(-1).after_move(), Score::Mate(1));
+    assert_eq!(Score::Mate(1).after_move(), Score::Mate(0));
+
+    // White mates in two, so once White has moved, Black is mated in one.
+    let pos = position("7k/8/5K2/8/8/8/8/6R1 w - - 0 1");
+    let mut searcher = new_searcher();
+    let config = SearchConfig::default();
+    let result = searcher.search(&pos, &[], &SearchLimits::depth(4), &config, false, false);
+    assert_eq!(result.score, Score::Mate(2));
+    let mut after = pos.clone();
+    after.play_unchecked(result.best_move.unwrap());
+    let reply = new_searcher().search(&after, &[], &SearchLimits::depth(4), &config, false, false);
+    assert_eq!(result.score.after_move(), Score::Mate(-1));
+    assert_eq!(reply.score, result.score.after_move());
+}
+
+#[test]
+fn test_score_win_probability_round_trips() {
+    assert_eq!(Score::Cp(0).win_probability(), 0.5);
+    assert!((Score::Cp(400).win_probability() - 1.0 / (1.0 + (-1.0f64).exp())).abs() < 1e-12);
+    assert_eq!(Score::Mate(2).win_probability(), 1.0);
+    assert_eq!(Score::Mate(-1).win_probability(), 0.0);
+    for cp in [-2500, -120, 0, 35, 900] {
+        assert_eq!(Score::from_win_probability(Score::Cp(cp).win_probability()), Score::Cp(cp));
+    }
+    assert_eq!(Score::from_win_probability(1.0), Score::Cp(MAX_WIN_PROBABILITY_CP));
+    assert_eq!(Score::from_win_probability(0.0), Score::Cp(-MAX_WIN_PROBABILITY_CP));
+}
+
+#[test]
+fn test_search_result_reports_stats() {
+    let pos = position("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+    let mut searcher = new_searcher();
+    let config = SearchConfig::default();
+    let result = searcher.search(&pos, &[], &SearchLimits::depth(3), &config, false, false);
+    assert_eq!(result.stats.depth, 3);
+    assert!(result.stats.seldepth >= 3);
+    assert!(result.stats.nodes > result.stats.qnodes);
+    assert!(result.stats.beta_cutoffs >= result.stats.first_move_cutoffs);
+    assert_eq!(result.pv.first().copied(), result.best_move);
+}
+
+#[test]
+fn test_principal_variation_is_legal_line() {
+    let pos = position("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+    let mut searcher = new_searcher();
+    let config = SearchConfig::default();
+    let result = searcher.search(&pos, &[], &SearchLimits::depth(4), &config, false, false);
+    assert!(result.pv.len() > 1);
+    assert_eq!(result.pv[0], result.best_move.unwrap());
+
+    let mut line = pos.clone();
+    for &m in &result.pv {
+        assert!(line.is_legal(m), "illegal PV move {m}");
+        line.play_unchecked(m);
+    }
+    assert_eq!(result.pv_san(&pos).len(), result.pv.len());
+    assert_eq!(result.pv_uci().len(), result.pv.len());
+}
+
+#[test]
+fn test_mate_pv_ends_in_checkmate() {
+    let pos = position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    let mut searcher = new_searcher();
+    let config = SearchConfig::default();
+    let result = searcher.search(&pos, &[], &SearchLimits::depth(3), &config, false, false);
+    assert_eq!(result.pv_san(&pos), vec!["Ra8#".to_string()]);
+}
+
+#[test]
+fn test_move_ordering_ranks_captures() {
+    // exd5 and Qxd5 both win the queen, Qxf7+ loses ours to the king.
+    let pos = position("4k3/5p2/8/3q3Q/4P3/8/8/4K3 w - - 0 1");
+    let mut searcher = new_searcher();
+    let config = SearchConfig { use_killer_moves: true, ..SearchConfig::default() };
+    let killer = Move::Normal {
+        role: Role::King,
+        from: Square::E1,
+        capture: None,
+        to: Square::F1,
+        promotion: None,
+    };
+    searcher.killer_moves[0][0] = Some(killer);
+
+    let mut moves: Vec<Move> = pos.legal_moves().into_iter().collect();
+    searcher.order_moves(&mut moves, &pos, 0, &config, None);
+    let index_of = |from: Square, to: Square| moves.iter().position(|m| m.from() == Some(from) && m.to() == to).unwrap();
+
+    assert_eq!(index_of(Square::E4, Square::D5), 0);
+    assert_eq!(index_of(Square::H5, Square::D5), 1);
+    assert!(index_of(Square::E1, Square::F1) < index_of(Square::H5, Square::F7));
+}
+
+#[test]
+fn test_move_ordering_ranks_promotions() {
+    let pos = position("8/4P1k1/8/8/8/8/8/4K3 w - - 0 1");
+    let searcher = new_searcher();
+    let config = SearchConfig::default();
+    let mut moves: Vec<Move> = pos.legal_moves().into_iter().collect();
+    searcher.order_moves(&mut moves, &pos, 0, &config, None);
+    assert_eq!(moves[0].promotion(), Some(Role::Queen));
+    assert!(moves[moves.len() - 3..].iter().all(|m| m.promotion().is_some()));
+}
+
+#[test]
+fn test_quiescence_scores_mate_by_ply() {
+    let pos = position("R5k1/5ppp/8/8/8/8/8/6K1 b - - 0 1");
+    let mut searcher = new_searcher();
+    let config = SearchConfig::default();
+    let score = searcher.quiescence_search(&pos, -MATE_SCORE, MATE_SCORE, 3, &config, false);
+    assert_eq!(score, -MATE_SCORE + 3);
+}
+
+#[test]
+fn test_quiescence_searches_evasions_in_check() {
+    // Black is a queen for a rook up, but in check: the only evasion Qe8 is met by Rxe8#.
+    let pos = position("R5k1/5ppp/8/3p4/4q3/3P4/5PPP/6K1 b - - 0 1");
+    let mut searcher = new_searcher();
+    let config = SearchConfig::default();
+    let standing_pat = searcher.evaluate_with_cache(&pos, &config);
+    let score = searcher.quiescence_search(&pos, -MATE_SCORE, MATE_SCORE, 1, &config, false);
+    assert!(standing_pat > 0);
+    assert_eq!(score, -MATE_SCORE + 3);
+}
+
+#[test]
+fn test_quiescence_includes_queen_promotions() {
+    let pos = position("8/4P1k1/8/8/8/8/8/4K3 w - - 0 1");
+    let mut searcher = new_searcher();
+    let config = SearchConfig::default();
+    let score = searcher.quiescence_search(&pos, -MATE_SCORE, MATE_SCORE, 1, &config, false);
+    assert!(score > 700, "score {score}");
+}
+
+#[test]
+fn test_quiescence_checks_at_first_ply() {
+    let pos = position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    let mut searcher = new_searcher();
+    let without_checks = SearchConfig::default();
+    let score = searcher.quiescence_search(&pos, -MATE_SCORE, MATE_SCORE, 2, &without_checks, false);
+    assert!(score < MATE_THRESHOLD);
+
+    let with_checks = SearchConfig { use_qs_checks: true, ..SearchConfig::default() };
+    let score = searcher.quiescence_search(&pos, -MATE_SCORE, MATE_SCORE, 2, &with_checks, false);
+    assert_eq!(score, MATE_SCORE - 3);
+}
+
+#[test]
+fn test_quiescence_checks_leave_out_castling() {
+    // Castling short checks along the f-file, as does Rf1.
+    let pos = position("5k2/8/8/8/8/8/8/4K2R w K - 0 1");
+    let moves = quiescence_moves(&pos, true);
+    assert!(moves.contains(&uci_move(&pos, "h1f1")));
+    assert!(!moves.iter().any(|m| m.is_castle()));
+}
+
+#[test]
+fn test_quiescence_scores_stalemate_with_contempt() {
+    let pos = position("k7/2Q5/1K6/8/8/8/8/8 b - - 0 1");
+    let mut searcher = new_searcher();
+    // The searcher has not searched yet, so White is the root side and Black gains the contempt.
+    let config = SearchConfig { contempt_factor: 20, ..SearchConfig::default() };
+    let score = searcher.quiescence_search(&pos, -MATE_SCORE, MATE_SCORE, 1, &config, false);
+    assert_eq!(score, 20);
+}
+
+#[test]
+fn test_fifty_move_rule_scores_draw_with_contempt() {
+    // White is a queen up, but every move completes fifty moves without a capture or pawn move.
+    let pos = position("k7/8/8/8/8/8/8/3Q3K w - - 99 80");
+    let mut searcher = new_searcher();
+    let config = SearchConfig { contempt_factor: 0, ..SearchConfig::default() };
+    let result = searcher.search(&pos, &[], &SearchLimits::depth(3), &config, false, false);
+    assert_eq!(result.score, Score::Cp(0));
+
+    let config = SearchConfig { contempt_factor: 20, ..SearchConfig::default() };
+    let result = searcher.search(&pos, &[], &SearchLimits::depth(3), &config, false, false);
+    assert_eq!(result.score, Score::Cp(-20));
+}
+
+#[test]
+fn test_repetition_of_game_position_is_a_draw() {
+    let mut pos = Chess::default();
+    let mut history = Vec::new();
+    for uci in ["g1f3", "g8f6", "f3g1"] {
+        history.push(pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal));
+        let m = uci.parse::<shakmaty::uci::UciMove>().unwrap().to_move(&pos).unwrap();
+        pos.play_unchecked(m);
+    }
+
+    // Black is happy to draw, so it repeats the starting position with Ng8.
+    let mut searcher = new_searcher();
+    let config = SearchConfig { contempt_factor: -300, ..SearchConfig::default() };
+    let result = searcher.search(&pos, &history, &SearchLimits::depth(3), &config, false, false);
+    assert_eq!(result.best_move.map(|m| (m.from(), m.to())), Some((Some(Square::F6), Square::G8)));
+    assert_eq!(result.score, Score::Cp(300));
+}
+
+#[test]
+fn test_mcts_repetition_of_game_position_is_a_draw() {
+    let mut pos = Chess::default();
+    let mut history = Vec::new();
+    for uci in ["g1f3", "g8f6", "f3g1"] {
+        history.push(pos.zobrist_hash::<Zobrist64>(EnPassantMode::Legal));
+        let m = uci.parse::<shakmaty::uci::UciMove>().unwrap().to_move(&pos).unwrap();
+        pos.play_unchecked(m);
+    }
+
+    // As above, Black repeats the starting position, and the draw is scored with contempt.
+    for algorithm in [SearchAlgorithm::Mcts, SearchAlgorithm::MctsAb] {
+        let mut searcher = mcts::MctsSearcher::with_tables(SearchTables::with_size_mb(4));
+        let config = SearchConfig { contempt_factor: -300, search_algorithm: algorithm.clone(), ..mcts_config(400) };
+        let result = searcher.search(&pos, &history, &SearchLimits::depth(1), &config, false, false);
+        assert_eq!(result.best_move.map(|m| (m.from(), m.to())), Some((Some(Square::F6), Square::G8)), "{algorithm:?}");
+        assert_eq!(result.score, Score::Cp(300), "{algorithm:?}");
+    }
+}
+
+#[test]
+fn test_lazy_smp_search_finds_mate() {
+    let pos = position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    let config = SearchConfig::default();
+
+    let mut searcher = new_searcher();
+    let single = searcher.search(&pos, &[], &SearchLimits::depth(4), &config, false, false);
+
+    let mut searcher = new_searcher();
+    let limits = SearchLimits { threads: 4, ..SearchLimits::depth(4) };
+    let result = searcher.search(&pos, &[], &limits, &config, false, false);
+    assert_eq!(result.score, Score::Mate(1));
+    assert_eq!(result.best_move, single.best_move);
+    assert_eq!(result.stats.depth, 4);
+}
+
+#[test]
+fn test_check_extension_finds_mate_beyond_nominal_depth() {
+    // Rb8+ Rxb8 Rxb8# needs three plies. Without quiescence the final capture is beyond the horizon.
+    let pos = position("r5k1/5ppp/8/8/8/8/1R6/1R4K1 w - - 0 1");
+    let base = SearchConfig { use_quiescence_search: false, use_futility_pruning: false, ..SearchConfig::default() };
+    let config = SearchConfig { use_check_extensions: true, ..base.clone() };
+    let result = new_searcher().search(&pos, &[], &SearchLimits::depth(2), &config, false, false);
+    assert_eq!(result.score, Score::Mate(2));
+
+    let config = SearchConfig { use_check_extensions: false, ..base };
+    let result = new_searcher().search(&pos, &[], &SearchLimits::depth(2), &config, false, false);
+    assert!(result.score.centipawns() < MATE_THRESHOLD);
+}
+
+#[test]
+fn test_recapture_and_passed_pawn_extensions() {
+    let config = SearchConfig {
+        use_check_extensions: false,
+        use_recapture_extensions: true,
+        use_passed_pawn_extensions: true,
+        ..SearchConfig::default()
+    };
+    let mut searcher = new_searcher();
+    let extension_of = |searcher: &PvsSearcher, pos: &Chess, uci: &str| {
+        let m = uci.parse::<shakmaty::uci::UciMove>().unwrap().to_move(pos).unwrap();
+        let mut new_pos = pos.clone();
+        new_pos.play_unchecked(m);
+        searcher.extension(pos, 1, &config, m, &new_pos, None)
+    };
+
+    // White just played Nxd5; exd5 recaptures on the same square.
+    let pos = position("4k3/8/4p3/3N4/8/8/8/4K3 b - - 0 1");
+    searcher.played_moves[0] = Some(Move::Normal {
+        role: Role::Knight,
+        from: Square::C3,
+        capture: Some(Role::Pawn),
+        to: Square::D5,
+        promotion: None,
+    });
+    assert_eq!(extension_of(&searcher, &pos, "e6d5"), 1);
+    assert_eq!(extension_of(&searcher, &pos, "e8d7"), 0);
+
+    // The a-pawn reaches the seventh rank, the h-pawn only the sixth.
+    let pos = position("4k3/8/P7/7P/8/8/8/4K3 w - - 0 1");
+    assert_eq!(extension_of(&searcher, &pos, "a6a7"), 1);
+    assert_eq!(extension_of(&searcher, &pos, "h5h6"), 0);
+}
+
+#[test]
+fn test_search_with_all_extensions_enabled() {
+    let pos = position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    let config = SearchConfig {
+        use_check_extensions: true,
+        use_recapture_extensions: true,
+        use_passed_pawn_extensions: true,
+        use_singular_extensions: true,
+        ..SearchConfig::default()
+    };
+    let result = new_searcher().search(&pos, &[], &SearchLimits::depth(7), &config, false, false);
+    assert_eq!(result.score, Score::Mate(1));
+    assert_eq!(result.stats.depth, 7);
+}
+
+#[test]
+fn test_configs_saved_before_extensions_load_without_them() {
+    let mut json = serde_json::to_value(SearchConfig::default()).unwrap();
+    let fields = json.as_object_mut().unwrap();
+    for gene in ["use_check_extensions", "use_recapture_extensions", "use_passed_pawn_extensions", "use_singular_extensions"] {
+        assert_eq!(fields.remove(gene), Some(serde_json::Value::Bool(false)), "{gene}");
+    }
+    let config: SearchConfig = serde_json::from_value(json).unwrap();
+    assert_eq!(config, SearchConfig::default());
+}
+
+#[test]
+fn test_pruning_toggles_keep_forced_mate() {
+    let pos = position("r5k1/5ppp/8/8/8/8/1R6/1R4K1 w - - 0 1");
+    let toggles: [fn(&mut SearchConfig); 5] = [
+        |c| c.use_reverse_futility_pruning = true,
+        |c| c.use_razoring = true,
+        |c| c.use_late_move_pruning = true,
+        |c| c.use_probcut = true,
+        |c| c.use_adaptive_nmp = true,
+    ];
+    for enable in toggles {
+        let mut config = SearchConfig::default();
+        enable(&mut config);
+        let result = new_searcher().search(&pos, &[], &SearchLimits::depth(5), &config, false, false);
+        assert_eq!(result.score, Score::Mate(2));
+    }
+}
+
+#[test]
+fn test_pruning_reduces_nodes() {
+    let pos = position("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+    let base = SearchConfig::default();
+    let pruned = SearchConfig {
+        use_reverse_futility_pruning: true,
+        use_razoring: true,
+        use_late_move_pruning: true,
+        use_probcut: true,
+        ..SearchConfig::default()
+    };
+    let full = new_searcher().search(&pos, &[], &SearchLimits::depth(5), &base, false, false);
+    let result = new_searcher().search(&pos, &[], &SearchLimits::depth(5), &pruned, false, false);
+    assert!(result.best_move.is_some());
+    assert!(result.stats.nodes < full.stats.nodes, "{} >= {}", result.stats.nodes, full.stats.nodes);
+}
+
+#[test]
+fn test_aspiration_window_widens_on_fail_high() {
+    // Once the mate is seen, the score jumps far above the previous iteration's window.
+    let pos = position("r5k1/5ppp/8/8/8/8/1R6/1R4K1 w - - 0 1");
+    let config = SearchConfig {
+        use_aspiration_windows: true,
+        aspiration_window: 10,
+        use_quiescence_search: false,
+        use_futility_pruning: false,
+        use_check_extensions: false,
+        ..SearchConfig::default()
+    };
+    let result = new_searcher().search(&pos, &[], &SearchLimits::depth(4), &config, false, false);
+    assert_eq!(result.score, Score::Mate(2));
+    assert!(result.stats.aspiration_researches > 0);
+}
+
+#[test]
+fn test_root_search_stops_at_fail_high() {
+    // Every move scores above a window just above a mate, so the first one fails high.
+    let pos = Chess::default();
+    let config = SearchConfig::default();
+    let args = PvsRootSearchArgs {
+        pos: &pos,
+        depth: 2,
+        config: &config,
+        alpha: -MATE_SCORE,
+        beta: -MATE_SCORE + 1,
+        build_tree: true,
+        verbose: false,
+    };
+    let (best_move, score, tree) = new_searcher().pvs_root_search(args);
+    assert!(best_move.is_some());
+    assert!(score > -MATE_SCORE);
+    assert_eq!(tree.children.len(), 1);
+}
+
+#[test]
+fn test_aspiration_window_matches_full_window_score() {
+    let pos = position("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+    let full = SearchConfig::default();
+    let aspiration = SearchConfig { use_aspiration_windows: true, aspiration_window: 10, ..SearchConfig::default() };
+    let expected = new_searcher().search(&pos, &[], &SearchLimits::depth(4), &full, false, false);
+    let result = new_searcher().search(&pos, &[], &SearchLimits::depth(4), &aspiration, false, false);
+    assert_eq!(result.score, expected.score);
+}
+
+fn uci_move(pos: &Chess, uci: &str) -> Move {
+    uci.parse::<shakmaty::uci::UciMove>().unwrap().to_move(pos).unwrap()
+}
+
+#[test]
+fn test_history_updates_are_bounded_and_aged() {
+    let pos = Chess::default();
+    let m = uci_move(&pos, "g1f3");
+    let previous = uci_move(&pos, "e2e4");
+    let mut history = MoveHistory::new();
+    for _ in 0..1_000 {
+        history.update_butterfly(Color::White, &m, MoveHistory::bonus(20));
+        history.update_continuation(Color::White, &m, &previous, -MoveHistory::bonus(20));
+    }
+    let score = history.butterfly(Color::White, &m);
+    assert!((1..=HISTORY_MAX).contains(&score));
+    let malus = history.continuation(Color::White, &m, &previous);
+    assert!((-HISTORY_MAX..0).contains(&malus));
+
+    history.age();
+    assert_eq!(history.butterfly(Color::White, &m), score / 2);
+    assert_eq!(history.butterfly(Color::Black, &m), 0);
+}
+
+#[test]
+fn test_counter_move_ordered_after_killers() {
+    let pos = position("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+    let config = SearchConfig {
+        use_killer_moves: true,
+        use_counter_moves: true,
+        use_history_heuristic: true,
+        ..SearchConfig::default()
+    };
+    let mut searcher = new_searcher();
+    let previous = Move::Normal { role: Role::Knight, from: Square::B8, capture: None, to: Square::C6, promotion: None };
+    searcher.played_moves[0] = Some(previous);
+    let killer = uci_move(&pos, "d2d3");
+    let counter = uci_move(&pos, "f1b5");
+    searcher.killer_moves[1][0] = Some(killer);
+    searcher.history.set_counter_move(Color::White, &previous, counter);
+
+    let mut moves: Vec<Move> = pos.legal_moves().into_iter().collect();
+    searcher.order_moves(&mut moves, &pos, 1, &config, None);
+    let quiet_moves: Vec<Move> = moves.into_iter().filter(|m| !m.is_capture()).collect();
+    assert_eq!(quiet_moves[0], killer);
+    assert_eq!(quiet_moves[1], counter);
+}
+
+#[test]
+fn test_search_with_history_heuristics() {
+    let pos = position("r1bqkbnr/pppp1ppp/2n5/4p3/4P3/5N2/PPPP1PPP/RNBQKB1R w KQkq - 2 3");
+    let config = SearchConfig {
+        use_history_heuristic: true,
+        use_counter_moves: true,
+        use_continuation_history: true,
+        ..SearchConfig::default()
+    };
+    let mut searcher = new_searcher();
+    let result = searcher.search(&pos, &[], &SearchLimits::depth(5), &config, false, false);
+    assert!(result.best_move.is_some());
+    assert_eq!(result.stats.depth, 5);
+}
+
+fn mcts_config(simulations: u32) -> SearchConfig {
+    SearchConfig { search_algorithm: SearchAlgorithm::Mcts, mcts_simulations: simulations, ..SearchConfig::default() }
+}
+
+#[test]
+fn test_mcts_finds_back_rank_mate() {
+    let pos = position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    let mut searcher = mcts::MctsSearcher::with_tables(SearchTables::with_size_mb(4));
+    let result = searcher.search(&pos, &[], &SearchLimits::depth(1), &mcts_config(200), false, false);
+    assert_eq!(result.best_move, Some(uci_move(&pos, "a1a8")));
+    assert_eq!(result.pv.first(), result.best_move.as_ref());
+    assert!(result.stats.qnodes > 0);
+}
+
+#[test]
+fn test_mcts_priors_lead_to_winning_capture() {
+    // The black queen is only defended by the king, so the rook wins it for itself.
+    let pos = position("3qk3/8/8/8/8/8/3R4/4K3 w - - 0 1");
+    let mut searcher = mcts::MctsSearcher::with_tables(SearchTables::with_size_mb(4));
+    for exploration in [*MCTS_EXPLORATION_RANGE.start(), *MCTS_EXPLORATION_RANGE.end()] {
+        let config = SearchConfig { mcts_exploration: exploration, ..mcts_config(300) };
+        let result = searcher.search(&pos, &[], &SearchLimits::depth(1), &config, false, false);
+        assert_eq!(result.best_move, Some(uci_move(&pos, "d2d8")), "exploration {exploration}");
+    }
+}
+
+#[test]
+fn test_mcts_ab_leaf_search_finds_mate_in_two() {
+    // Rook a7 or b7 cuts the king off on the back rank, and the other rook mates next move.
+    let pos = position("7k/8/8/8/8/8/R7/1R4K1 w - - 0 1");
+    let config = SearchConfig { search_algorithm: SearchAlgorithm::MctsAb, mcts_leaf_depth: 2, ..mcts_config(100) };
+    let mut searcher = mcts::MctsSearcher::with_tables(SearchTables::with_size_mb(4));
+    let result = searcher.search(&pos, &[], &SearchLimits::depth(1), &config, false, false);
+    let mating_moves = [uci_move(&pos, "a2a7"), uci_move(&pos, "b1b7")];
+    assert!(mating_moves.contains(&result.best_move.unwrap()), "{:?}", result.best_move);
+    // The leaf searches go beyond the tree and the quiescence search.
+    assert!(result.stats.nodes > result.stats.qnodes + 100);
+}
+
+#[test]
+fn test_parallel_mcts_finds_back_rank_mate() {
+    let pos = position("6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1");
+    let mut searcher = mcts::MctsSearcher::with_tables(SearchTables::with_size_mb(4));
+    let limits = SearchLimits { threads: 4, ..SearchLimits::depth(1) };
+    let result = searcher.search(&pos, &[], &limits, &mcts_config(2000), false, false);
+    assert_eq!(result.best_move, Some(uci_move(&pos, "a1a8")));
+    assert_eq!(result.pv.first(), result.best_move.as_ref());
+}
+
+#[test]
+fn test_mcts_reports_scores_on_the_pvs_scale() {
+    // Up a rook, without a mate in reach, so both searches score the material.
+    let pos = position("4k3/pp6/8/8/8/8/PP6/R3K3 w - - 0 1");
+    let pvs_score = new_searcher().search(&pos, &[], &SearchLimits::depth(3), &SearchConfig::default(), false, false).score;
+    let mut searcher = mcts::MctsSearcher::with_tables(SearchTables::with_size_mb(4));
+    let result = searcher.search(&pos, &[], &SearchLimits::depth(1), &mcts_config(300), true, false);
+
+    let (Score::Cp(pvs_cp), Score::Cp(mcts_cp)) = (pvs_score, result.score) else {
+        panic!("expected centipawn scores, got {pvs_score} and {}", result.score);
+    };
+    assert!(pvs_cp > 300, "{pvs_cp}");
+    assert!(mcts_cp > 300 && mcts_cp < 2 * pvs_cp, "{mcts_cp} vs {pvs_cp}");
+    let Score::Cp(root_cp) = result.tree.unwrap().score else { panic!() };
+    assert!(root_cp > 300, "{root_cp}");
+}
@@ -0,0 +1,165 @@
+// src/game/search/transposition_table.rs
+
+use shakmaty::{Move, Role, Square};
+use shakmaty::zobrist::Zobrist64;
+use super::hash_table::{HashTable, Replacement, TableEntry};
+use crate::constants::{MATE_SCORE, MATE_THRESHOLD, TRANSPOSITION_TABLE_MB};
+
+/// How a stored score relates to the true minimax value of the position.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Bound {
+    /// The score is exact (a PV node).
+    Exact,
+    /// The score is a lower bound (the search failed high).
+    Lower,
+    /// The score is an upper bound (the search failed low).
+    Upper,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct TtEntry {
+    pub depth: u8,
+    pub score: i32,
+    pub bound: Bound,
+    pub best_move: Option<Move>,
+}
+
+// Packed layout: move in bits 0-23, score in bits 24-45, depth in bits 46-53, bound in 54-55.
+// The top byte is left to the table.
+const MOVE_BITS: u64 = 0xFF_FFFF;
+const SCORE_SHIFT: u32 = 24;
+const SCORE_BITS: u64 = 0x3F_FFFF;
+const DEPTH_SHIFT: u32 = 46;
+const BOUND_SHIFT: u32 = 54;
+
+impl TableEntry for TtEntry {
+    fn pack(self) -> u64 {
+        debug_assert!(self.score.abs() < 1 << 21);
+        let bound = match self.bound {
+            Bound::Exact => 0,
+            Bound::Lower => 1,
+            Bound::Upper => 2,
+        };
+        encode_move(self.best_move)
+            | (self.score as u64 & SCORE_BITS) << SCORE_SHIFT
+            | (self.depth as u64) << DEPTH_SHIFT
+            | bound << BOUND_SHIFT
+    }
+
+    fn unpack(data: u64) -> Self {
+        // Shift the 22-bit score to the top of an i32 and back to restore its sign.
+        let score = (((data >> SCORE_SHIFT) & SCORE_BITS) as u32 as i32) << 10 >> 10;
+        let bound = match (data >> BOUND_SHIFT) & 3 {
+            0 => Bound::Exact,
+            1 => Bound::Lower,
+            _ => Bound::Upper,
+        };
+        Self {
+            depth: (data >> DEPTH_SHIFT) as u8,
+            score,
+            bound,
+            best_move: decode_move(data & MOVE_BITS),
+        }
+    }
+
+    fn priority(&self) -> u8 {
+        self.depth
+    }
+}
+
+/// Packs a move into 23 bits: the kind in bits 0-1, then the role, origin, captured role,
+/// destination and promotion. Zero means no move.
+fn encode_move(m: Option<Move>) -> u64 {
+    let role = |role: Option<Role>| role.map_or(0, |r| r as u64);
+    let squares = |from: Square, to: Square| (from as u64) << 5 | (to as u64) << 14;
+    match m {
+        Some(Move::Normal { role: moved, from, capture, to, promotion }) => {
+            1 | (moved as u64) << 2 | squares(from, to) | role(capture) << 11 | role(promotion) << 20
+        }
+        Some(Move::EnPassant { from, to }) => 2 | squares(from, to),
+        Some(Move::Castle { king, rook }) => 3 | squares(king, rook),
+        Some(Move::Put { .. }) | None => 0,
+    }
+}
+
+fn decode_move(bits: u64) -> Option<Move> {
+    let role = |shift: u32| Role::try_from((bits >> shift) & 7).ok();
+    let from = Square::new(((bits >> 5) & 63) as u32);
+    let to = Square::new(((bits >> 14) & 63) as u32);
+    match bits & 3 {
+        1 => Some(Move::Normal { role: role(2)?, from, capture: role(11), to, promotion: role(20) }),
+        2 => Some(Move::EnPassant { from, to }),
+        3 => Some(Move::Castle { king: from, rook: to }),
+        _ => None,
+    }
+}
+
+/// A transposition table that the threads of a search share without locking.
+///
+/// Entries are bucketed and replaced by depth, so deep results survive shallow ones. Entries
+/// from earlier searches age out, so the table can be kept for a whole game.
+pub struct TranspositionTable {
+    table: HashTable<TtEntry>,
+}
+
+impl Default for TranspositionTable {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TranspositionTable {
+    pub fn new() -> Self {
+        Self::with_size_mb(TRANSPOSITION_TABLE_MB)
+    }
+
+    pub fn with_size_mb(size_mb: usize) -> Self {
+        Self {
+            table: HashTable::with_size_mb(size_mb, Replacement::PriorityPreferred),
+        }
+    }
+
+    /// The number of entries the table can hold.
+    #[cfg_attr(not(test), allow(dead_code))]
+    pub fn capacity(&self) -> usize {
+        self.table.capacity()
+    }
+
+    pub fn probe(&self, hash: &Zobrist64) -> Option<TtEntry> {
+        self.table.probe(hash.0)
+    }
+
+    pub fn store(&self, hash: Zobrist64, entry: TtEntry) {
+        // Keep the previous best move if the new search did not produce one.
+        let best_move = entry.best_move.or_else(|| self.probe(&hash).and_then(|e| e.best_move));
+        self.table.store(hash.0, TtEntry { best_move, ..entry });
+    }
+
+    /// Called at the start of each search, so that older entries are replaced first.
+    pub fn new_search(&self) {
+        self.table.new_search();
+    }
+}
+
+/// Converts a mate score relative to the root into one relative to the current node,
+/// so that it stays valid when the position is reached through a different path.
+pub fn score_to_tt(score: i32, ply: u8) -> i32 {
+    if score >= MATE_THRESHOLD {
+        score + ply as i32
+    } else if score <= -MATE_THRESHOLD {
+        score - ply as i32
+    } else {
+        score
+    }
+}
+
+/// Inverse of `score_to_tt`.
+pub fn score_from_tt(score: i32, ply: u8) -> i32 {
+    if score >= MATE_THRESHOLD {
+        (score - ply as i32).min(MATE_SCORE)
+    } else if score <= -MATE_THRESHOLD {
+        (score + ply as i32).max(-MATE_SCORE)
+    } else {
+        score
+    }
+}
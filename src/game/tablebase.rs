@@ -0,0 +1,179 @@
+// src/game/tablebase.rs
+
+use shakmaty::{Chess, Move, Position};
+use shakmaty_syzygy::{AmbiguousWdl, Tablebase, Wdl};
+use std::io;
+use std::path::Path;
+
+/// Syzygy endgame tablebases, used by the search and to adjudicate GA games.
+///
+/// Probes fail quietly: a position that is not covered, or whose tables are missing or
+/// unreadable, probes as `None` and is searched as usual.
+pub struct Tablebases {
+    tablebase: Tablebase<Chess>,
+}
+
+impl Tablebases {
+    /// Loads the tables in the directory `path`. Fails if it contains none.
+    pub fn open(path: impl AsRef<Path>) -> io::Result<Self> {
+        let mut tablebase = Tablebase::new();
+        if tablebase.add_directory(path)? == 0 {
+            return Err(io::Error::new(io::ErrorKind::NotFound, "no tablebase files found"));
+        }
+        Ok(Self { tablebase })
+    }
+
+    /// The number of pieces of the largest loaded table.
+    pub fn max_pieces(&self) -> usize {
+        self.tablebase.max_pieces()
+    }
+
+    /// Whether `pos` has few enough pieces to be probed. Tables do not cover castling rights.
+    pub fn covers(&self, pos: &Chess) -> bool {
+        pos.board().occupied().count() <= self.max_pieces() && pos.castles().is_empty()
+    }
+
+    /// The result of `pos` for the side to move, assuming it was reached by a capture or a pawn
+    /// move. Only needs WDL tables.
+    pub fn probe_wdl_after_zeroing(&self, pos: &Chess) -> Option<Wdl> {
+        if !self.covers(pos) {
+            return None;
+        }
+        self.tablebase.probe_wdl_after_zeroing(pos).ok()
+    }
+
+    /// The result of `pos` for the side to move under the fifty-move rule, given its halfmove
+    /// clock. Positions with a clock of zero only need WDL tables, others DTZ tables as well.
+    pub fn probe_wdl(&self, pos: &Chess) -> Option<AmbiguousWdl> {
+        if pos.halfmoves() == 0 {
+            self.probe_wdl_after_zeroing(pos).map(AmbiguousWdl::from)
+        } else if self.covers(pos) {
+            self.tablebase.probe_wdl(pos).ok()
+        } else {
+            None
+        }
+    }
+
+    /// The distance in plies to the next capture or pawn move of `pos` under optimal play, for
+    /// the side to move. Positive if it wins, negative if it loses and zero for a draw. Needs
+    /// DTZ tables.
+    pub fn probe_dtz(&self, pos: &Chess) -> Option<i32> {
+        if !self.covers(pos) {
+            return None;
+        }
+        self.tablebase.probe_dtz(pos).ok().map(|dtz| dtz.ignore_rounding().0)
+    }
+
+    /// The legal moves of `pos` that keep the best result the tables allow under the fifty-move
+    /// rule. Winning moves are narrowed to those that reset the halfmove clock soonest, losing
+    /// moves to those that hold out longest. Returns `None` if any move cannot be probed.
+    pub fn root_moves(&self, pos: &Chess) -> Option<Vec<Move>> {
+        let ranked = pos
+            .legal_moves()
+            .into_iter()
+            .map(|m| {
+                let mut after = pos.clone();
+                after.play_unchecked(m);
+                self.probe_wdl(&after).map(|wdl| (m, after, -wdl))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        let best = ranked.iter().map(|(_, _, wdl)| *wdl).max()?;
+        let kept: Vec<_> = ranked.into_iter().filter(|(_, _, wdl)| *wdl == best).collect();
+        if best == AmbiguousWdl::Draw {
+            return Some(kept.into_iter().map(|(m, _, _)| m).collect());
+        }
+
+        // The sign of the DTZ matches the result, so the lowest value is the quickest win or the
+        // slowest loss.
+        let dtzs = kept
+            .iter()
+            .map(|(m, after, wdl)| self.dtz_after_move(m, after, *wdl))
+            .collect::<Option<Vec<_>>>()?;
+        let lowest = dtzs.iter().copied().min()?;
+        Some(kept.into_iter().zip(dtzs).filter(|&(_, dtz)| dtz == lowest).map(|((m, _, _), _)| m).collect())
+    }
+
+    /// The DTZ for the side playing `m`, which leads to `after` with the result `wdl` for that
+    /// side. Mates and moves that reset the halfmove clock count as a single ply.
+    fn dtz_after_move(&self, m: &Move, after: &Chess, wdl: AmbiguousWdl) -> Option<i32> {
+        if after.is_checkmate() || m.is_zeroing() {
+            return Some(if wdl > AmbiguousWdl::Draw { 1 } else { -1 });
+        }
+        let dtz = -self.probe_dtz(after)?;
+        Some(dtz + dtz.signum())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use shakmaty::{fen::Fen, uci::UciMove, CastlingMode};
+    use tempfile::tempdir;
+
+    const TABLES_PATH: &str = "test-data/syzygy";
+
+    fn position(fen: &str) -> Chess {
+        let fen: Fen = fen.parse().unwrap();
+        fen.into_position(CastlingMode::Standard).unwrap()
+    }
+
+    #[test]
+    fn test_open_fails_without_tables() {
+        let dir = tempdir().unwrap();
+        std::fs::write(dir.path().join("README.txt"), "not a table").unwrap();
+        let err = Tablebases::open(dir.path()).err().unwrap();
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+        assert!(Tablebases::open(dir.path().join("missing")).is_err());
+    }
+
+    #[test]
+    fn test_root_moves_keep_the_quickest_win() {
+        let tablebases = Tablebases::open(TABLES_PATH).unwrap();
+        assert_eq!(tablebases.max_pieces(), 3);
+
+        // Every queen move that keeps the queen wins, but only Qb8 mates at once.
+        let pos = position("7k/8/6K1/8/8/8/8/1Q6 w - - 0 1");
+        assert_eq!(tablebases.probe_wdl(&pos), Some(AmbiguousWdl::Win));
+        assert_eq!(tablebases.probe_dtz(&pos), Some(1));
+        let mate = "b1b8".parse::<UciMove>().unwrap().to_move(&pos).unwrap();
+        assert_eq!(tablebases.root_moves(&pos), Some(vec![mate]));
+
+        let pos = position("8/8/8/3k4/8/8/8/K6R w - - 0 1");
+        let root_moves = tablebases.root_moves(&pos).unwrap();
+        assert!(!root_moves.is_empty());
+        let dtz = tablebases.probe_dtz(&pos).unwrap();
+        for m in root_moves {
+            let mut after = pos.clone();
+            after.play_unchecked(m);
+            assert_eq!(tablebases.probe_dtz(&after), Some(-(dtz - 1)), "{m}");
+        }
+    }
+
+    #[test]
+    fn test_root_moves_keep_the_slowest_loss() {
+        let tablebases = Tablebases::open(TABLES_PATH).unwrap();
+        let pos = position("8/8/8/3k4/8/8/8/KQ6 b - - 0 1");
+        let dtz_after = |m: &Move| {
+            let mut after = pos.clone();
+            after.play_unchecked(*m);
+            tablebases.probe_dtz(&after).unwrap()
+        };
+        let slowest = pos.legal_moves().iter().map(dtz_after).max().unwrap();
+        assert_eq!(tablebases.probe_dtz(&pos), Some(-(slowest + 1)));
+
+        let root_moves = tablebases.root_moves(&pos).unwrap();
+        assert!(!root_moves.is_empty());
+        assert!(root_moves.iter().all(|m| dtz_after(m) == slowest));
+        assert!(root_moves.len() < pos.legal_moves().len());
+    }
+
+    #[test]
+    fn test_root_moves_keep_every_drawing_move() {
+        let tablebases = Tablebases::open(TABLES_PATH).unwrap();
+        // Black takes the undefended rook, every other move loses.
+        let pos = position("8/8/8/8/8/8/1k6/1R5K b - - 0 1");
+        let capture = "b2b1".parse::<UciMove>().unwrap().to_move(&pos).unwrap();
+        assert_eq!(tablebases.probe_wdl(&pos), Some(AmbiguousWdl::Draw));
+        assert_eq!(tablebases.root_moves(&pos), Some(vec![capture]));
+    }
+}
@@ -11,6 +11,8 @@ mod sts;
 mod worker;
 
 use clap::Parser;
+use constants::OPENING_BOOK_PLIES;
+use game::opening_book::BookSelection;
 
 
 #[derive(Parser, Debug)]
@@ -20,10 +22,26 @@ struct Args {
     #[arg(long)]
     tablebase_path: Option<String>,
 
-    /// Path to the PGN opening book file
+    /// Path to the Polyglot opening book file
     #[arg(long)]
     opening_book: Option<String>,
 
+    /// Number of plies of each GA game taken from the opening book
+    #[arg(long, default_value_t = OPENING_BOOK_PLIES)]
+    book_depth: usize,
+
+    /// How to choose between the book moves for a position
+    #[arg(long, value_enum, default_value_t = BookSelection::Weighted)]
+    book_selection: BookSelection,
+
+    /// Path to an EPD or PGN file of start positions for GA games
+    #[arg(long)]
+    opening_suite: Option<String>,
+
+    /// Play each opening of the suite a second time with the colors reversed
+    #[arg(long)]
+    reverse_colors: bool,
+
     /// Run in mock mode for frontend verification, accepts A, B, or C
     #[arg(long)]
     mock_scenario: Option<String>,
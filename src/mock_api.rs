@@ -8,6 +8,7 @@ use crate::{
     server::{ApiGenerationDetails, ApiIndividual, GenerationSummary},
 };
 use lazy_static::lazy_static;
+use std::collections::BTreeMap;
 
 // Scenario C: A fresh server state with no historical data.
 lazy_static! {
@@ -63,6 +64,7 @@ lazy_static! {
             })
             .collect(),
         matches: vec![],
+        termination_counts: BTreeMap::new(),
         sts_results: None,
     };
     pub static ref MOCK_GENERATION_DETAILS_B1: ApiGenerationDetails = ApiGenerationDetails {
@@ -77,6 +79,7 @@ lazy_static! {
             })
             .collect(),
         matches: vec![],
+        termination_counts: BTreeMap::new(),
         sts_results: Some(vec![]),
     };
     pub static ref MOCK_INDIVIDUAL_B0_0: Individual = Individual {
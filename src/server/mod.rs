@@ -1,7 +1,7 @@
 // src/server/mod.rs
 
 use crate::event::{Event, SelectionAlgorithm, WebsocketState, WsMessage, EVENT_BROKER};
-use crate::ga::{Generation, GenerationConfig, Match, SelectionModeConfig};
+use crate::ga::{termination_counts, Generation, GenerationConfig, Match, SelectionModeConfig, Termination};
 use crate::game::search::SearchConfig;
 use crate::sts::{StsResult, StsRunner};
 use actix::{Actor, AsyncContext, Handler, Message, StreamHandler};
@@ -9,7 +9,7 @@ use actix_files as fs;
 use actix_web::{web, App, Error, HttpRequest, HttpResponse, HttpServer, Responder};
 use actix_web_actors::ws;
 use serde::{Deserialize, Serialize};
-use std::collections::HashSet;
+use std::collections::{BTreeMap, HashSet};
 use std::hash::{Hash, Hasher};
 use std::path::Path;
 use std::{fs as std_fs, io, time::Duration};
@@ -48,6 +48,8 @@ pub struct ApiGenerationDetails {
     pub round: u32,
     pub population: Vec<ApiIndividual>,
     pub matches: Vec<Match>,
+    /// The number of completed matches that ended in each way.
+    pub termination_counts: BTreeMap<Termination, usize>,
     pub sts_results: Option<Vec<StsResult>>,
 }
 
@@ -189,6 +191,7 @@ async fn get_generation_details(
                     generation_index: gen.generation_index,
                     round: gen.round,
                     population: api_population,
+                    termination_counts: termination_counts(&gen.matches),
                     matches: gen.matches,
                     sts_results: gen.sts_results,
                 };
@@ -368,7 +371,8 @@ async fn run_sts_test_logic(gen_id: u32, ind_id: u32) -> Result<u64, HttpRespons
         .iter()
         .find(|i| i.id == ind_id as usize)
     {
-        let mut runner = StsRunner::new(individual.config.clone());
+        // A single run has the machine to itself, so each position gets every core.
+        let mut runner = StsRunner::new(individual.config.clone()).with_threads(num_cpus::get());
         let config_hash = runner.config_hash();
         tokio::spawn(async move {
             runner.run().await;
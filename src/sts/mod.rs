@@ -1,15 +1,67 @@
 // src/sts/mod.rs
 
+use crate::constants::{HASH_MEMORY_BUDGET_MB, STS_MOVE_TIME_MS};
 use crate::event::{Event, StsUpdate, EVENT_BROKER};
-use crate::game::search::{mcts::MctsSearcher, SearchConfig, Searcher};
-use shakmaty::{san::San, Chess};
+use crate::game::search::{SearchConfig, SearchLimits, SearchTables};
+use crate::worker::{push_job, Job, SearcherHandle};
 use serde::{Deserialize, Serialize};
+use shakmaty::{san::San, Chess};
 use std::collections::hash_map::DefaultHasher;
+use std::fmt;
+use std::fs;
 use std::hash::{Hash, Hasher};
 use std::io;
 use std::path::{Path, PathBuf};
-use std::{fs};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::oneshot;
+
+/// How much work the searcher may do on each STS position.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum StsLimit {
+    MoveTime { millis: u64 },
+    Nodes { count: u64 },
+}
+
+impl StsLimit {
+    /// The limits of the search of one position. The individual's depth stays an upper bound,
+    /// as in GA games, and `stop` cancels the search.
+    fn search_limits(self, config: &SearchConfig, stop: Option<Arc<AtomicBool>>) -> SearchLimits {
+        let limits = SearchLimits {
+            stop,
+            ..SearchLimits::depth(config.search_depth)
+        };
+        match self {
+            StsLimit::MoveTime { millis } => SearchLimits {
+                movetime: Some(Duration::from_millis(millis)),
+                ..limits
+            },
+            StsLimit::Nodes { count } => SearchLimits {
+                nodes: Some(count),
+                ..limits
+            },
+        }
+    }
+}
+
+impl Default for StsLimit {
+    fn default() -> Self {
+        StsLimit::MoveTime {
+            millis: STS_MOVE_TIME_MS,
+        }
+    }
+}
 
+impl fmt::Display for StsLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            StsLimit::MoveTime { millis } => write!(f, "{millis} ms"),
+            StsLimit::Nodes { count } => write!(f, "{count} nodes"),
+        }
+    }
+}
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct StsResult {
@@ -19,12 +71,21 @@ pub struct StsResult {
     pub correct_moves: usize,
     pub total_positions: usize,
     pub elo: Option<f64>,
+    /// The limit each position was searched with. Results saved before limits were recorded
+    /// have none, and are not resumed.
+    #[serde(default)]
+    pub limit: Option<StsLimit>,
 }
 
 pub struct StsRunner {
     config: SearchConfig,
     config_hash: u64,
     result: StsResult,
+    stop: Option<Arc<AtomicBool>>,
+    threads: usize,
+    /// The size of the runner's hash tables. Runners of a population run one per worker, so
+    /// each gets a worker's share of `HASH_MEMORY_BUDGET_MB`.
+    tables_mb: usize,
 }
 
 impl StsRunner {
@@ -40,15 +101,49 @@ impl StsRunner {
             correct_moves: 0,
             total_positions: 0,
             elo: None,
+            limit: Some(StsLimit::default()),
         };
 
         Self {
             config,
             config_hash,
             result,
+            stop: None,
+            threads: 1,
+            tables_mb: HASH_MEMORY_BUDGET_MB / num_cpus::get().max(1),
+        }
+    }
+
+    /// Makes `stop` cancel the run. The position being searched is not counted, so that a
+    /// later run resumes from it.
+    pub fn with_stop_signal(mut self, stop: Arc<AtomicBool>) -> Self {
+        self.stop = Some(stop);
+        self
+    }
+
+    /// Searches each position on `threads` threads, which run a Lazy SMP search with PVS.
+    /// Meant for analysing one individual. Population runs keep a thread per search, like GA
+    /// games.
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads.max(1);
+        self
+    }
+
+    /// The limits of the search of each position.
+    fn search_limits(&self) -> SearchLimits {
+        let limit = self.result.limit.unwrap_or_default();
+        SearchLimits {
+            threads: self.threads,
+            ..limit.search_limits(&self.config, self.stop.clone())
         }
     }
 
+    fn is_stopped(&self) -> bool {
+        self.stop
+            .as_ref()
+            .is_some_and(|stop| stop.load(Ordering::Relaxed))
+    }
+
     pub fn config_hash(&self) -> u64 {
         self.config_hash
     }
@@ -63,8 +158,10 @@ impl StsRunner {
         let result_path = results_dir.join(format!("{}.json", self.config_hash));
         if result_path.exists() {
             if let Ok(json) = fs::read_to_string(&result_path) {
-                if let Ok(result) = serde_json::from_str::<StsResult>(&json) {
-                    self.result = result;
+                match serde_json::from_str::<StsResult>(&json) {
+                    // Results found with other limits are not comparable, so they start over.
+                    Ok(result) if result.limit == self.result.limit => self.result = result,
+                    _ => {}
                 }
             }
         }
@@ -77,9 +174,24 @@ impl StsRunner {
             }
         };
 
-        self.result.total_positions = epd_files.iter().map(|f| parse_epd(f).map(|p| p.len()).unwrap_or(0)).sum();
-
-        let mut mcts_searcher = MctsSearcher::new();
+        self.result.total_positions = epd_files
+            .iter()
+            .map(|f| parse_epd(f).map(|p| p.len()).unwrap_or(0))
+            .sum();
+
+        // The individual's own algorithm searches every position, on the worker pool like in
+        // GA games. It keeps its tables for the whole run.
+        let searcher = SearcherHandle::for_config(
+            &self.config,
+            SearchTables::for_algorithm(&self.config.search_algorithm, self.tables_mb),
+        );
+        let limits = self.search_limits();
+        println!(
+            "[STS] Searching with {:?} for {} per position on {} threads",
+            self.config.search_algorithm,
+            self.result.limit.unwrap_or_default(),
+            self.threads
+        );
 
         let mut current_position_index = 0;
 
@@ -99,13 +211,25 @@ impl StsRunner {
                 }
 
                 let fen = shakmaty::fen::Fen::from_position(&pos, shakmaty::EnPassantMode::Legal);
-                let (best_move, _, _, _) = mcts_searcher.search(
-                    &pos,
-                    self.config.search_depth,
-                    &self.config,
-                    false,
-                    false,
-                );
+                let (result_tx, result_rx) = oneshot::channel();
+                push_job(Job::FindBestMove {
+                    pos: pos.clone(),
+                    history: Vec::new(),
+                    config: self.config.clone(),
+                    limits: limits.clone(),
+                    searcher: searcher.clone(),
+                    // Only the best move counts here, so the search skips the tree and the logs.
+                    build_tree: false,
+                    verbose: false,
+                    result_tx,
+                });
+                // A dropped sender means the worker failed, which counts as a wrong move.
+                let best_move = result_rx.await.ok().and_then(|result| result.best_move);
+                if self.is_stopped() {
+                    let json = serde_json::to_string_pretty(&self.result).unwrap();
+                    fs::write(&result_path, &json).expect("Failed to save STS result");
+                    return None;
+                }
 
                 let (is_correct, move_san) = if let Some(m) = best_move {
                     let san = San::from_move(&pos, m);
@@ -131,21 +255,21 @@ impl StsRunner {
                 self.result.completed_positions += 1;
                 current_position_index += 1;
 
-            let progress =
-                self.result.completed_positions as f64 / self.result.total_positions as f64;
-            EVENT_BROKER.publish(Event::StsUpdate(StsUpdate {
-                config_hash: self.result.config_hash,
-                progress,
-                score: self.result.correct_moves,
-                total: self.result.total_positions,
-                elo: None,
-            }));
-
-            // Save progress every 10 positions
-            if self.result.completed_positions % 10 == 0 {
-                let json = serde_json::to_string_pretty(&self.result).unwrap();
-                fs::write(&result_path, &json).expect("Failed to save STS result");
-            }
+                let progress =
+                    self.result.completed_positions as f64 / self.result.total_positions as f64;
+                EVENT_BROKER.publish(Event::StsUpdate(StsUpdate {
+                    config_hash: self.result.config_hash,
+                    progress,
+                    score: self.result.correct_moves,
+                    total: self.result.total_positions,
+                    elo: None,
+                }));
+
+                // Save progress every 10 positions
+                if self.result.completed_positions.is_multiple_of(10) {
+                    let json = serde_json::to_string_pretty(&self.result).unwrap();
+                    fs::write(&result_path, &json).expect("Failed to save STS result");
+                }
             }
         }
 
@@ -169,7 +293,6 @@ impl StsRunner {
     }
 }
 
-
 fn get_epd_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
     let mut files = Vec::new();
     if dir.is_dir() {
@@ -185,8 +308,8 @@ fn get_epd_files(dir: &Path) -> io::Result<Vec<PathBuf>> {
 }
 
 fn parse_epd(file_path: &Path) -> Result<Vec<(Chess, String)>, String> {
-    let content = fs::read_to_string(file_path)
-        .map_err(|e| format!("Failed to read EPD file: {e}"))?;
+    let content =
+        fs::read_to_string(file_path).map_err(|e| format!("Failed to read EPD file: {e}"))?;
 
     let mut positions = Vec::new();
     for line in content.lines() {
@@ -202,10 +325,73 @@ fn parse_epd(file_path: &Path) -> Result<Vec<(Chess, String)>, String> {
         let fen_str = parts[0];
         let best_move_str = parts[1].split(';').next().unwrap_or("").trim();
 
-        let fen: shakmaty::fen::Fen = fen_str.parse().map_err(|e| format!("Failed to parse EPD line: {e}"))?;
-        let pos: Chess = fen.into_position(shakmaty::CastlingMode::Standard).map_err(|e| format!("Failed to setup position: {e}"))?;
+        let fen: shakmaty::fen::Fen = fen_str
+            .parse()
+            .map_err(|e| format!("Failed to parse EPD line: {e}"))?;
+        let pos: Chess = fen
+            .into_position(shakmaty::CastlingMode::Standard)
+            .map_err(|e| format!("Failed to setup position: {e}"))?;
         positions.push((pos, best_move_str.to_string()));
     }
 
     Ok(positions)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::game::search::{PvsSearcher, Score, Searcher};
+
+    #[test]
+    fn test_results_without_a_limit_still_load() {
+        let mut json =
+            serde_json::to_value(StsRunner::new(SearchConfig::default()).result).unwrap();
+        assert_eq!(
+            json["limit"],
+            serde_json::json!({ "move_time": { "millis": STS_MOVE_TIME_MS } })
+        );
+        json.as_object_mut().unwrap().remove("limit");
+        let result: StsResult = serde_json::from_value(json).unwrap();
+        assert_eq!(result.limit, None);
+    }
+
+    #[test]
+    fn test_limit_bounds_each_search() {
+        let config = SearchConfig::default();
+        let limits = StsLimit::Nodes { count: 5_000 }.search_limits(&config, None);
+        assert_eq!((limits.nodes, limits.movetime), (Some(5_000), None));
+        let stop = Arc::new(AtomicBool::new(true));
+        let limits = StsLimit::default().search_limits(&config, Some(stop));
+        assert!(limits.is_stopped());
+        assert_eq!(
+            limits.movetime,
+            Some(Duration::from_millis(STS_MOVE_TIME_MS))
+        );
+        assert_eq!(limits.max_depth, config.search_depth);
+    }
+
+    #[test]
+    fn test_runners_on_every_worker_stay_within_the_hash_budget() {
+        let runner = StsRunner::new(SearchConfig::default());
+        assert!(runner.tables_mb * num_cpus::get() <= HASH_MEMORY_BUDGET_MB);
+    }
+
+    #[test]
+    fn test_threads_run_a_lazy_smp_search() {
+        let runner = StsRunner::new(SearchConfig::default());
+        assert_eq!(runner.search_limits().threads, 1);
+
+        let runner = StsRunner::new(SearchConfig::default()).with_threads(4);
+        let limits = runner.search_limits();
+        assert_eq!(limits.threads, 4);
+        let fen: shakmaty::fen::Fen = "6k1/5ppp/8/8/8/8/8/R5K1 w - - 0 1".parse().unwrap();
+        let pos: Chess = fen.into_position(shakmaty::CastlingMode::Standard).unwrap();
+        let limits = SearchLimits {
+            max_depth: 3,
+            ..limits
+        };
+        let mut searcher = PvsSearcher::with_tables(SearchTables::with_size_mb(4));
+        let result = searcher.search(&pos, &[], &limits, &runner.config, false, false);
+        assert_eq!(result.score, Score::Mate(1));
+    }
+}
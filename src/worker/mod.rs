@@ -1,18 +1,19 @@
 #![cfg_attr(test, allow(dead_code))]
 
-use crate::game::search::{MoveTreeNode, SearchConfig, SearchAlgorithm};
-use crate::game::search::evaluation_cache::EvaluationCache;
-use crate::game::search::{mcts::MctsSearcher, PvsSearcher, Searcher};
+use crate::game::search::{SearchConfig, SearchAlgorithm, SearchLimits};
+use crate::game::search::{mcts::MctsSearcher, PvsSearcher, Searcher, SearchTables};
+use std::fmt;
 use crossbeam_channel::{Receiver, Sender};
 use lazy_static::lazy_static;
 use serde::Serialize;
-use shakmaty::{Chess, Move};
+use shakmaty::Chess;
+use shakmaty::zobrist::Zobrist64;
 use std::panic::{self, AssertUnwindSafe};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use tokio::sync::oneshot;
 
-pub type SearchResult = (Option<Move>, i32, Option<MoveTreeNode>, Option<String>);
+pub use crate::game::search::SearchResult;
 
 #[derive(Debug, Clone, Serialize)]
 pub enum Status {
@@ -26,14 +27,48 @@ pub struct WorkerStatus {
     pub status: Status,
 }
 
+/// A player's searcher, shared between the jobs of one game.
+///
+/// Searchers hold state that depends on the player: cached evaluations computed with its
+/// weights, its transposition table and its move history. Jobs carry their player's searcher so
+/// that workers never reuse that state for another individual.
+#[derive(Clone)]
+pub struct SearcherHandle(Arc<Mutex<dyn Searcher>>);
+
+impl SearcherHandle {
+    /// Creates a searcher for the algorithm of `config`, working with `tables`.
+    pub fn for_config(config: &SearchConfig, tables: SearchTables) -> Self {
+        match config.search_algorithm {
+            SearchAlgorithm::Pvs => Self(Arc::new(Mutex::new(PvsSearcher::with_tables(tables)))),
+            SearchAlgorithm::Mcts | SearchAlgorithm::MctsAb => {
+                Self(Arc::new(Mutex::new(MctsSearcher::with_tables(tables))))
+            }
+        }
+    }
+}
+
+impl fmt::Debug for SearcherHandle {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("SearcherHandle")
+    }
+}
+
 /// Represents a unit of work that can be processed by the worker pool.
 #[derive(Debug)]
 pub enum Job {
     /// A job to find the best move for a given chess position.
     FindBestMove {
         pos: Chess,
+        // Hashes of the positions since the last irreversible move, oldest first.
+        history: Vec<Zobrist64>,
         config: SearchConfig,
-        // Channel to send the result (best move, score, search tree, stats) back.
+        limits: SearchLimits,
+        searcher: SearcherHandle,
+        // Whether the search records its tree of moves in the result.
+        build_tree: bool,
+        // Whether the search logs its progress.
+        verbose: bool,
+        // Channel to send the result (best move, score, PV, search tree, stats) back.
         result_tx: oneshot::Sender<SearchResult>,
     },
 }
@@ -69,10 +104,6 @@ impl WorkerPool {
             let job_rx = JOB_QUEUE.1.clone();
 
             let _handle = thread::spawn(move || {
-                let mut pvs_searcher =
-                    PvsSearcher::with_shared_cache(Arc::new(Mutex::new(EvaluationCache::new())));
-                let mut mcts_searcher = MctsSearcher::new();
-
                 while let Ok(job) = job_rx.recv() {
                     let job_description = format!("{job:?}");
 
@@ -83,31 +114,24 @@ impl WorkerPool {
                         }
                     }
 
-                    let (pos, config, result_tx) = match job {
+                    let (pos, history, config, limits, searcher, build_tree, verbose, result_tx) = match job {
                         Job::FindBestMove {
                             pos,
+                            history,
                             config,
+                            limits,
+                            searcher,
+                            build_tree,
+                            verbose,
                             result_tx,
-                        } => (pos, config, result_tx),
+                        } => (pos, history, config, limits, searcher, build_tree, verbose, result_tx),
                     };
 
                     let result = panic::catch_unwind(AssertUnwindSafe(|| {
-                        match config.search_algorithm {
-                            SearchAlgorithm::Pvs => pvs_searcher.search(
-                                &pos,
-                                config.search_depth,
-                                &config,
-                                true,
-                                false,
-                            ),
-                            SearchAlgorithm::Mcts => mcts_searcher.search(
-                                &pos,
-                                config.search_depth,
-                                &config,
-                                true,
-                                true,
-                            ),
-                        }
+                        // A searcher whose previous search panicked is poisoned. Its game ended
+                        // with that failed search, so the state inside no longer matters.
+                        let mut searcher = searcher.0.lock().unwrap_or_else(|e| e.into_inner());
+                        searcher.search(&pos, &history, &limits, &config, build_tree, verbose)
                     }));
 
                     match result {
@@ -123,16 +147,8 @@ impl WorkerPool {
                                 "Box<dyn Any>"
                             };
                             println!("MCTS task errored: {panic_info}");
-                            pvs_searcher = PvsSearcher::with_shared_cache(Arc::new(Mutex::new(
-                                EvaluationCache::new(),
-                            )));
-                            mcts_searcher = MctsSearcher::new();
-                            let _ = result_tx.send((
-                                None,
-                                0,
-                                None,
-                                Some(format!("Worker panicked: {panic_info}")),
-                            ));
+                            // An empty result makes the caller treat the search as failed.
+                            let _ = result_tx.send(SearchResult::default());
                         }
                     }
 